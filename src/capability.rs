@@ -0,0 +1,78 @@
+use crate::Model;
+
+// What a model can do beyond plain text completion, shown as a badge in the model list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    Vision,
+    Tools,
+    Embedding,
+}
+
+pub const ALL: [Capability; 3] = [Capability::Vision, Capability::Tools, Capability::Embedding];
+
+impl Capability {
+    pub fn glyph(self) -> char {
+        match self {
+            Capability::Vision => '◉',
+            Capability::Tools => 'λ',
+            Capability::Embedding => '≡',
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Capability::Vision => "vision",
+            Capability::Tools => "tools",
+            Capability::Embedding => "embedding",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vision" => Some(Capability::Vision),
+            "tools" => Some(Capability::Tools),
+            "embedding" => Some(Capability::Embedding),
+            _ => None,
+        }
+    }
+}
+
+// Capabilities as reported by /api/show
+pub fn from_show(capabilities: &[String]) -> Vec<Capability> {
+    capabilities
+        .iter()
+        .filter_map(|name| Capability::from_name(name))
+        .collect()
+}
+
+// Older servers don't report capabilities, so fall back to what the family and name suggest
+pub fn guess(model: &Model) -> Vec<Capability> {
+    let families = model.details.families.as_deref().unwrap_or_default();
+    let mut capabilities = vec![];
+    if families
+        .iter()
+        .any(|family| family == "clip" || family == "mllama")
+        || model.name.contains("llava")
+        || model.name.contains("vision")
+    {
+        capabilities.push(Capability::Vision);
+    }
+    if model.details.family.contains("bert") || model.name.contains("embed") {
+        capabilities.push(Capability::Embedding);
+    }
+    capabilities
+}
+
+pub fn badges(capabilities: &[Capability]) -> String {
+    capabilities
+        .iter()
+        .map(|capability| capability.glyph())
+        .collect()
+}
+
+pub fn legend() -> String {
+    ALL.iter()
+        .map(|capability| format!("{} {}", capability.glyph(), capability.label()))
+        .collect::<Vec<_>>()
+        .join("  ")
+}
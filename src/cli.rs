@@ -0,0 +1,58 @@
+// Command-line flags; anything not given keeps the interactive defaults
+#[derive(Debug, Default)]
+pub struct Args {
+    pub debug: bool,
+    pub one_shot: bool,
+    pub dry_run: bool,
+    // selected on start; with a prompt, the reply is printed to stdout and the TUI never starts
+    pub model: Option<String>,
+    pub prompt: Option<String>,
+    pub pull: bool,
+    // wins over OLLAMA_HOST, which wins over the config
+    pub host: Option<String>,
+    // for this run only, instead of the model's saved one
+    pub system: Option<String>,
+    pub help: bool,
+}
+
+pub const USAGE: &str = "\
+Usage: ollama-tui-rs [OPTIONS]
+
+Options:
+  -H, --host <url>     Ollama server, instead of OLLAMA_HOST or the config's host
+      --debug          open the raw request/response overlay on start
+      --oneshot        send every message without conversation history
+      --dry-run        show each request in the chat instead of sending it
+      --model <name>   start with this model selected, offering to pull it if it's missing
+      --pull           pull the --model without asking
+      --system <text>  system prompt for this session, instead of the model's own
+      --prompt <text>  print the reply to this prompt and exit, without the TUI (needs --model)
+  -h, --help           print this help";
+
+impl Args {
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| format!("{} needs a value\n\n{}", arg, USAGE))
+            };
+            match arg.as_str() {
+                "--debug" => parsed.debug = true,
+                "--oneshot" => parsed.one_shot = true,
+                "--dry-run" => parsed.dry_run = true,
+                "--model" => parsed.model = Some(value()?),
+                "--prompt" => parsed.prompt = Some(value()?),
+                "--pull" => parsed.pull = true,
+                "--system" => parsed.system = Some(value()?),
+                "-H" | "--host" => parsed.host = Some(value()?),
+                "-h" | "--help" => parsed.help = true,
+                other => return Err(format!("unknown argument: {}\n\n{}", other, USAGE)),
+            }
+        }
+        if parsed.prompt.is_some() && parsed.model.is_none() {
+            return Err(format!("--prompt needs --model\n\n{}", USAGE));
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,156 @@
+// Every slash command the input understands, used for completion and help
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+}
+
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "cancel",
+        usage: "/cancel",
+        description: "decline the tool call the model is waiting on",
+    },
+    CommandInfo {
+        name: "clear",
+        usage: "/clear",
+        description: "clear the conversation",
+    },
+    CommandInfo {
+        name: "context",
+        usage: "/context <turns|all>",
+        description: "previous exchanges sent with each message",
+    },
+    CommandInfo {
+        name: "delete",
+        usage: "/delete <model>",
+        description: "remove a model from the server's disk",
+    },
+    CommandInfo {
+        name: "dryrun",
+        usage: "/dryrun",
+        description: "toggle showing requests instead of sending them",
+    },
+    CommandInfo {
+        name: "export",
+        usage: "/export <path.md>",
+        description: "write the conversation out as Markdown",
+    },
+    CommandInfo {
+        name: "help",
+        usage: "/help",
+        description: "list these commands",
+    },
+    CommandInfo {
+        name: "image",
+        usage: "/image [path]",
+        description: "attach an image to the next message, empty to drop them",
+    },
+    CommandInfo {
+        name: "import",
+        usage: "/import <path>",
+        description: "load a conversation from JSON or Markdown",
+    },
+    CommandInfo {
+        name: "load",
+        usage: "/load <name>",
+        description: "replace the conversation with a saved one",
+    },
+    CommandInfo {
+        name: "model",
+        usage: "/model [name]",
+        description: "switch to another installed model",
+    },
+    CommandInfo {
+        name: "numctx",
+        usage: "/numctx [tokens]",
+        description: "context window for the selected model",
+    },
+    CommandInfo {
+        name: "oneshot",
+        usage: "/oneshot",
+        description: "toggle sending messages without history",
+    },
+    CommandInfo {
+        name: "pull",
+        usage: "/pull <model>",
+        description: "download a model",
+    },
+    CommandInfo {
+        name: "quit",
+        usage: "/quit",
+        description: "exit",
+    },
+    CommandInfo {
+        name: "retry",
+        usage: "/retry",
+        description: "resend the last request exactly as it was",
+    },
+    CommandInfo {
+        name: "save",
+        usage: "/save <name>",
+        description: "save the conversation to load it later",
+    },
+    CommandInfo {
+        name: "set",
+        usage: "/set [option] [value]",
+        description: "temperature, top_p, top_k, seed or num_ctx for the selected model",
+    },
+    CommandInfo {
+        name: "system",
+        usage: "/system [text]",
+        description: "system prompt for this session, empty to clear it",
+    },
+];
+
+// What /help shows, descriptions lined up after the longest usage
+pub fn help() -> String {
+    let width = COMMANDS
+        .iter()
+        .map(|command| command.usage.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut help = "Commands:".to_string();
+    for command in COMMANDS {
+        help.push_str(&format!(
+            "\n{:width$}  {}",
+            command.usage, command.description
+        ));
+    }
+    help
+}
+
+// Commands matching a partially typed `/name`, or nothing once an argument has started
+pub fn completions(input: &str) -> Vec<&'static CommandInfo> {
+    let Some(typed) = input.strip_prefix('/') else {
+        return vec![];
+    };
+    if typed.contains(char::is_whitespace) {
+        return vec![];
+    }
+
+    COMMANDS
+        .iter()
+        .filter(|command| command.name.starts_with(typed))
+        .collect()
+}
+
+// What Tab turns the input into: the full command when unambiguous, else the common prefix
+pub fn complete(input: &str) -> Option<String> {
+    let matches = completions(input);
+    match matches.as_slice() {
+        [] => None,
+        [command] => Some(format!("/{} ", command.name)),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.name, |common, command| {
+                let length = common
+                    .chars()
+                    .zip(command.name.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                &common[..length]
+            });
+            Some(format!("/{}", common))
+        }
+    }
+}
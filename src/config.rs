@@ -0,0 +1,135 @@
+use std::{collections::BTreeMap, fs, io, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{keymap::KeyBinding, template::Template, ApiFlavor, ModelOptions, StreamFormat, Tool};
+
+// User settings, stored as TOML under $XDG_CONFIG_HOME/ollama-tui-rs/config.toml
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Config {
+    // Ollama server, e.g. "http://10.0.0.2:11434" or "unix:///run/ollama.sock"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    // e.g. "http://proxy.corp:3128"; overrides HTTP_PROXY/HTTPS_PROXY, hosts in NO_PROXY still skip it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    // seconds a model list, show or delete may take; replies stream for as long as they need
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_timeout: Option<u64>,
+    // "ndjson" as Ollama streams, or "sse" for gateways that send OpenAI-style events
+    pub stream_format: StreamFormat,
+    // "ollama", or "openai" for /v1/chat/completions on Ollama or any compatible backend
+    pub api: ApiFlavor,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    pub favorites: Vec<String>,
+    // how many previous user/assistant exchanges go with each request; 0 sends only the prompt,
+    // unset sends the whole conversation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_turns: Option<usize>,
+    // default context window for models without their own num_ctx setting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    // force ASCII indicators and no colors, as when NO_COLOR is set
+    pub plain: bool,
+    // append every chat request and response chunk to an NDJSON file; off by default, it grows fast
+    pub transcript: bool,
+    // defaults to transcript.ndjson next to the config file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_path: Option<PathBuf>,
+    // estimated tokens above which sending asks for confirmation; defaults to the context window
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub large_prompt_tokens: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub templates: Vec<Template>,
+    // offered to the model with every request, as [[tools]] tables
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
+    // action name to key(s), e.g. edit = "i"; unlisted actions keep their default keys
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub keybindings: BTreeMap<String, KeyBinding>,
+    // keyed by model name, e.g. [models."llama3.2:latest"]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub models: BTreeMap<String, ModelSettings>,
+}
+
+// Settings remembered per model and applied when that model is selected
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct ModelSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<ModelOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("ollama-tui-rs").join("config.toml"))
+    }
+
+    // A missing file is not an error, it just means nothing has been configured yet
+    pub fn load() -> io::Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        match fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn transcript_path(&self) -> Option<PathBuf> {
+        self.transcript_path
+            .clone()
+            .or_else(|| Some(Self::path()?.parent()?.join("transcript.ndjson")))
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no config directory available")
+        })?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(path, content)
+    }
+
+    pub fn is_favorite(&self, model_name: &str) -> bool {
+        self.favorites.iter().any(|favorite| favorite == model_name)
+    }
+
+    pub fn toggle_favorite(&mut self, model_name: &str) {
+        if self.is_favorite(model_name) {
+            self.favorites.retain(|favorite| favorite != model_name);
+        } else {
+            self.favorites.push(model_name.to_string());
+        }
+    }
+
+    pub fn model_settings(&self, model_name: &str) -> ModelSettings {
+        self.models.get(model_name).cloned().unwrap_or_default()
+    }
+
+    // Default settings are dropped rather than written out as empty tables
+    pub fn set_model_settings(&mut self, model_name: &str, settings: ModelSettings) {
+        if settings == ModelSettings::default() {
+            self.models.remove(model_name);
+        } else {
+            self.models.insert(model_name.to_string(), settings);
+        }
+    }
+}
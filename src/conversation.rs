@@ -0,0 +1,120 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{Chat, ChatLog};
+
+const USER_HEADING: &str = "**You:**";
+const ASSISTANT_HEADING: &str = "**Assistant:**";
+
+// Reads a conversation written as JSON (a serialized ChatLog) or as Markdown with
+// `**You:**` / `**Assistant:**` headings; JSON keeps the original requests and responses
+// and the scroll position
+pub fn import(path: &Path) -> io::Result<ChatLog> {
+    let content = fs::read_to_string(path)?;
+
+    let is_markdown = path
+        .extension()
+        .is_some_and(|extension| extension == "md" || extension == "markdown");
+    let chat_log = if is_markdown {
+        ChatLog {
+            history: parse_markdown(&content),
+            scroll: None,
+        }
+    } else {
+        serde_json::from_str::<ChatLog>(&content)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?
+    };
+
+    if chat_log.history.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no conversation found in file",
+        ));
+    }
+    Ok(chat_log)
+}
+
+// /save and /load names map to $XDG_DATA_HOME/ollama-tui-rs/conversations/<name>.json
+pub fn saved_path(name: &str) -> io::Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a name can't be empty, start with a dot or contain a path separator",
+        ));
+    }
+    let data_dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME").ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no data directory available")
+        })?)
+        .join(".local")
+        .join("share"),
+    };
+    Ok(data_dir
+        .join("ollama-tui-rs")
+        .join("conversations")
+        .join(format!("{}.json", name)))
+}
+
+// Written in the same JSON that `import` reads back
+pub fn save(path: &Path, chat_log: &ChatLog) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(chat_log)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    fs::write(path, content)
+}
+
+// The same headings `import` reads, so an export can be loaded again. Command output
+// and tool results are left out; replies are written as they came, code fences included
+pub fn export_markdown(path: &Path, history: &[Chat], model: &str) -> io::Result<()> {
+    let exported_at = chrono::Local::now().format("%Y-%m-%d %H:%M");
+    let mut markdown = format!(
+        "# Conversation with {}\n\nExported {}\n",
+        model, exported_at
+    );
+    for chat in history {
+        let heading = match chat.author.as_str() {
+            "user" => USER_HEADING,
+            "assistant" => ASSISTANT_HEADING,
+            _ => continue,
+        };
+        markdown.push_str(&format!("\n{}\n\n{}\n", heading, chat.content.trim_end()));
+    }
+    fs::write(path, markdown)
+}
+
+fn parse_markdown(content: &str) -> Vec<Chat> {
+    let mut history: Vec<Chat> = vec![];
+    for line in content.lines() {
+        let author = match line.trim_end() {
+            USER_HEADING => Some("user"),
+            ASSISTANT_HEADING => Some("assistant"),
+            _ => None,
+        };
+
+        match (author, history.last_mut()) {
+            (Some(author), _) => history.push(Chat {
+                author: author.to_string(),
+                content: String::new(),
+                origin_content: vec![],
+                model_override: None,
+            }),
+            // text before the first heading (title, metadata) is not part of any turn
+            (None, None) => {}
+            (None, Some(chat)) => {
+                chat.content.push_str(line);
+                chat.content.push('\n');
+            }
+        }
+    }
+
+    // Markdown carries no raw requests/responses, so origin_content stays empty
+    for chat in &mut history {
+        chat.content = chat.content.trim().to_string();
+    }
+    history
+}
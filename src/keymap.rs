@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+// Something a key does in the model-select (normal) mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Edit,
+    ModelNext,
+    ModelPrevious,
+    SelectModel,
+    ModelDetails,
+    Continue,
+    Regenerate,
+    CopyReply,
+    SystemPrompt,
+    Favorite,
+    ScrollBottom,
+    ToggleWrap,
+    Templates,
+    Debug,
+    Search,
+    SearchNext,
+    SearchPrevious,
+    ScrollLeft,
+    ScrollRight,
+}
+
+// (action, config name, default keys)
+const ACTIONS: &[(Action, &str, &[&str])] = &[
+    (Action::Quit, "quit", &["Esc"]),
+    (Action::Edit, "edit", &["e"]),
+    (Action::ModelNext, "model_next", &["Down"]),
+    (Action::ModelPrevious, "model_previous", &["Up"]),
+    (Action::SelectModel, "select_model", &["Enter"]),
+    (Action::ModelDetails, "model_details", &["i"]),
+    (Action::Continue, "continue", &["c"]),
+    (Action::Regenerate, "regenerate", &["r"]),
+    (Action::CopyReply, "copy_reply", &["y"]),
+    (Action::SystemPrompt, "system_prompt", &["s"]),
+    (Action::Favorite, "favorite", &["f"]),
+    (Action::ScrollBottom, "scroll_bottom", &["G", "End"]),
+    (Action::ToggleWrap, "toggle_wrap", &["w"]),
+    (Action::Templates, "templates", &["t"]),
+    (Action::Debug, "debug", &["d"]),
+    (Action::Search, "search", &["/"]),
+    (Action::SearchNext, "search_next", &["n"]),
+    (Action::SearchPrevious, "search_previous", &["N"]),
+    (Action::ScrollLeft, "scroll_left", &["Left"]),
+    (Action::ScrollRight, "scroll_right", &["Right"]),
+];
+
+// One key or several, e.g. `edit = "i"` or `scroll_bottom = ["G", "End"]`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum KeyBinding {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyBinding {
+    fn keys(&self) -> &[String] {
+        match self {
+            KeyBinding::One(key) => std::slice::from_ref(key),
+            KeyBinding::Many(keys) => keys,
+        }
+    }
+}
+
+type Key = (KeyCode, KeyModifiers);
+
+pub struct Keymap {
+    actions: HashMap<Key, Action>,
+    // keys in the order they were given, the first one is shown in the hints
+    keys: HashMap<Action, Vec<Key>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&BTreeMap::new()).expect("default keybindings are valid")
+    }
+}
+
+impl Keymap {
+    // Actions not mentioned in `bindings` keep their default keys
+    pub fn new(bindings: &BTreeMap<String, KeyBinding>) -> Result<Self, String> {
+        if let Some(unknown) = bindings.keys().find(|name| {
+            !ACTIONS
+                .iter()
+                .any(|(_, action_name, _)| action_name == name)
+        }) {
+            return Err(format!("unknown keybinding action \"{}\"", unknown));
+        }
+
+        let mut keymap = Self {
+            actions: HashMap::new(),
+            keys: HashMap::new(),
+        };
+        for (action, name, defaults) in ACTIONS {
+            let keys: Vec<&str> = match bindings.get(*name) {
+                Some(binding) => binding.keys().iter().map(String::as_str).collect(),
+                None => defaults.to_vec(),
+            };
+            for key_name in keys {
+                let key = parse_key(key_name)
+                    .ok_or_else(|| format!("invalid key \"{}\" for {}", key_name, name))?;
+                if let Some(other) = keymap.actions.insert(key, *action) {
+                    return Err(format!(
+                        "\"{}\" is bound to both {} and {}",
+                        key_name,
+                        action_name(other),
+                        name
+                    ));
+                }
+                keymap.keys.entry(*action).or_default().push(key);
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.actions
+            .get(&normalize(key.code, key.modifiers))
+            .copied()
+    }
+
+    // How the first key of an action is written in the help line
+    pub fn label(&self, action: Action) -> String {
+        match self.keys.get(&action).and_then(|keys| keys.first()) {
+            Some((code, modifiers)) => key_label(*code, *modifiers),
+            None => "(unbound)".to_string(),
+        }
+    }
+}
+
+fn action_name(action: Action) -> &'static str {
+    ACTIONS
+        .iter()
+        .find(|(other, _, _)| *other == action)
+        .map(|(_, name, _)| *name)
+        .unwrap_or_default()
+}
+
+// Shift is already part of an uppercase character, so it's only kept for named keys
+fn normalize(code: KeyCode, modifiers: KeyModifiers) -> Key {
+    let mut modifiers =
+        modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+    if matches!(code, KeyCode::Char(_)) {
+        modifiers.remove(KeyModifiers::SHIFT);
+    }
+    (code, modifiers)
+}
+
+// Keys are written like "e", "G", "Esc", "PageDown", "F5" or "Ctrl+n"
+fn parse_key(name: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = name;
+    loop {
+        if let Some(stripped) = rest.strip_prefix("Ctrl+") {
+            modifiers |= KeyModifiers::CONTROL;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("Alt+") {
+            modifiers |= KeyModifiers::ALT;
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("Shift+") {
+            modifiers |= KeyModifiers::SHIFT;
+            rest = stripped;
+        } else {
+            break;
+        }
+    }
+
+    let code = match rest {
+        "Esc" => KeyCode::Esc,
+        "Enter" => KeyCode::Enter,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Space" => KeyCode::Char(' '),
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        _ => {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                (Some('F'), Some(_)) => KeyCode::F(rest[1..].parse().ok()?),
+                _ => return None,
+            }
+        }
+    };
+    Some(normalize(code, modifiers))
+}
+
+fn key_label(code: KeyCode, modifiers: KeyModifiers) -> String {
+    let name = match code {
+        KeyCode::Up => "▲".to_string(),
+        KeyCode::Down => "▼".to_string(),
+        KeyCode::Left => "◀".to_string(),
+        KeyCode::Right => "▶".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        other => format!("{:?}", other),
+    };
+    let mut label = String::new();
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        label.push_str("Ctrl+");
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        label.push_str("Alt+");
+    }
+    if modifiers.contains(KeyModifiers::SHIFT) {
+        label.push_str("Shift+");
+    }
+    label + &name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(pairs: &[(&str, KeyBinding)]) -> BTreeMap<String, KeyBinding> {
+        pairs
+            .iter()
+            .map(|(name, binding)| (name.to_string(), binding.clone()))
+            .collect()
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn rebinding_replaces_the_default_keys() {
+        let keymap = Keymap::new(&bindings(&[
+            ("edit", KeyBinding::One("a".to_string())),
+            (
+                "debug",
+                KeyBinding::Many(vec!["Ctrl+d".to_string(), "F5".to_string()]),
+            ),
+        ]))
+        .unwrap();
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('a'), KeyModifiers::NONE)),
+            Some(Action::Edit)
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('e'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::F(5), KeyModifiers::NONE)),
+            Some(Action::Debug)
+        );
+        assert_eq!(keymap.label(Action::Debug), "Ctrl+d");
+        // uppercase letters arrive with Shift, which the binding leaves out
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::ScrollBottom)
+        );
+    }
+
+    #[test]
+    fn conflicts_and_bad_names_are_rejected() {
+        let error = Keymap::new(&bindings(&[("edit", KeyBinding::One("c".to_string()))]))
+            .err()
+            .unwrap();
+        assert_eq!(error, "\"c\" is bound to both edit and continue");
+
+        let error = Keymap::new(&bindings(&[("jump", KeyBinding::One("j".to_string()))]))
+            .err()
+            .unwrap();
+        assert_eq!(error, "unknown keybinding action \"jump\"");
+
+        let error = Keymap::new(&bindings(&[(
+            "edit",
+            KeyBinding::One("Hyper+e".to_string()),
+        )]))
+        .err()
+        .unwrap();
+        assert_eq!(error, "invalid key \"Hyper+e\" for edit");
+    }
+}
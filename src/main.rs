@@ -1,26 +1,93 @@
-use std::{net::Shutdown, process::exit};
+mod capability;
+mod cli;
+mod command;
+mod config;
+mod conversation;
+mod keymap;
+mod openai;
+mod relative_time;
+mod search;
+mod status_bar;
+mod template;
+mod transcript;
 
+use std::{
+    collections::HashMap,
+    io::Write,
+    ops::Range,
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use capability::Capability;
+use cli::Args;
+use command::CommandInfo;
+use config::{Config, ModelSettings};
 use futures::StreamExt;
+use keymap::{Action, Keymap};
+use openai::CompletionChunk;
 use ratatui::{
-    buffer::Buffer,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    crossterm::event::{Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     layout::{Constraint, Flex, Layout, Margin, Position, Rect},
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
     widgets::{
-        Block, HighlightSpacing, List, ListItem, ListState, Paragraph, Scrollbar,
-        ScrollbarOrientation, ScrollbarState, StatefulWidget, Widget, Wrap,
+        Block, Clear, Gauge, HighlightSpacing, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Wrap,
     },
     DefaultTerminal, Frame,
 };
+use ratatui_image::{
+    picker::{Picker, ProtocolType},
+    protocol::Protocol,
+    Image, Resize,
+};
+use search::ChatSearch;
 use serde::{Deserialize, Serialize};
-use tokio_util::io::StreamReader;
+use status_bar::StatusBar;
+use template::Template;
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+use transcript::Transcript;
+
+// Recorded on a reply the user stopped with Esc
+const REPLY_STOPPED: &str = "stopped";
+
+// Recorded on a reply whose stream closed before the final chunk arrived
+const STREAM_ENDED_EARLY: &str = "stream ended before done";
+
+// Sent after the partial reply when the user asks a truncated reply to keep going
+const CONTINUE_PROMPT: &str =
+    "Continue exactly where your previous reply stopped, without repeating anything.";
+
+// Columns moved per Left/Right press when the chat is not wrapped
+const HORIZONTAL_SCROLL_STEP: usize = 4;
+
+// How long a typed model number waits for its next digit
+const MODEL_NUMBER_TIMEOUT: Duration = Duration::from_millis(800);
+
+// Cells reserved under an "[image]" placeholder when the terminal can draw graphics
+const IMAGE_PREVIEW_WIDTH: u16 = 32;
+const IMAGE_PREVIEW_HEIGHT: u16 = 8;
+
+// Input rows shown before the box stops growing and scrolls instead
+const MAX_INPUT_ROWS: usize = 8;
+
+// Ollama's default context window when no num_ctx is requested or configured
+const DEFAULT_NUM_CTX: usize = 2048;
 
 struct App {
     exit: bool,
     models_info: ModelInfo,
     selected_model: Model,
     ollama_api: OllamaApi,
+    config: Config,
+    // false when config.toml couldn't be read, so the defaults are never saved over it
+    config_loaded: bool,
+    model_settings: ModelSettings,
 
     last_chat_area_height: usize,
     last_chat_area_width: usize,
@@ -30,9 +97,130 @@ struct App {
     character_index: usize,
 
     chat_log: ChatLog,
+    status_message: Option<StatusMessage>,
 
     chat_scroll_state: ScrollbarState,
     chat_scroll: usize,
+    // wrapped line count of the chat as of the last draw
+    chat_line_count: usize,
+    // keep the newest line in view as the log grows
+    follow_tail: bool,
+    // chat_line_count when the bottom was last in view; anything past it is unread.
+    // None takes whatever the next draw shows as read
+    seen_line_count: Option<usize>,
+    chat_wrap: bool,
+    // horizontal offset, only used while wrapping is off
+    chat_scroll_x: usize,
+    // wrapped line where each history entry starts, as of the last draw
+    message_line_offsets: Vec<usize>,
+    search: Option<ChatSearch>,
+    // the history as of the last /save, /export or /load; a /clear that loses nothing doesn't ask
+    saved_history: Option<String>,
+
+    // background tasks report back through this channel
+    events_tx: UnboundedSender<AppEvent>,
+    events_rx: UnboundedReceiver<AppEvent>,
+    pull: Option<PullTask>,
+    confirm: Option<Confirm>,
+    template_picker: Option<ListState>,
+    template_fill: Option<TemplateFill>,
+    debug_overlay: bool,
+    debug_scroll: u16,
+    // index of the model whose details popup is open
+    model_details: Option<usize>,
+    // the input is expanded for writing a long prompt; Enter adds a line instead of sending
+    composing: bool,
+    // from focus change events; terminals that never report focus stay "focused"
+    focused: bool,
+    keymap: Keymap,
+    // whether the last request to the server got through; None before the first one
+    connected: Option<bool>,
+    transcript: Option<Transcript>,
+    // ASCII indicators and no colors, for NO_COLOR and limited terminals
+    plain: bool,
+    // the reply being streamed, if any
+    generation: Option<Generation>,
+    // the id most recently given to a generation or pull
+    last_task_id: u64,
+    // send each prompt on its own, without earlier turns
+    one_shot: bool,
+    // show the assembled request in the chat instead of sending it
+    dry_run: bool,
+    // set while the input holds the options for a regenerate, restored once it closes
+    regenerate_input: Option<String>,
+    // the same while the input holds the system prompt being edited
+    system_prompt_input: Option<String>,
+    // digits typed so far to pick a model by its number, and when the last one came
+    model_number: Option<(String, Instant)>,
+    // named with --model; selected once the list is loaded, or after it has been pulled
+    wanted_model: Option<String>,
+    // pull the wanted model without asking when it isn't installed
+    pull_wanted_model: bool,
+    // from --host or OLLAMA_HOST, used instead of the config's host
+    host: Option<String>,
+    // from --system or /system, used for the session instead of the model's own
+    system_prompt: Option<String>,
+    // base64 images from /image, sent with the next prompt
+    pending_images: Vec<String>,
+
+    // only set when the terminal speaks a graphics protocol (Kitty, iTerm2, Sixel)
+    image_picker: Option<Picker>,
+    // keyed by the base64 data; None remembers images that failed to decode
+    image_previews: HashMap<String, Option<Protocol>>,
+}
+
+// A yes/no question guarding a destructive action
+struct Confirm {
+    message: String,
+    action: ConfirmAction,
+}
+
+enum ConfirmAction {
+    ClearChat,
+    // the prompt and its @model override, if any
+    SendPrompt(String, Option<String>),
+    PullModel(String),
+    DeleteModel(String),
+}
+
+// Collects placeholder values one at a time through the input box
+struct TemplateFill {
+    template: Template,
+    placeholders: Vec<String>,
+    values: Vec<(String, String)>,
+    // restored if the fill is cancelled
+    previous_input: String,
+}
+
+// Chat and pull events carry the id of the task that sent them, so ones still queued from a
+// stopped task aren't taken for the task that replaced it
+enum AppEvent {
+    ChatChunk(u64, ChatResponse),
+    ChatFinished(u64, Result<(), std::io::Error>),
+    PullProgress(u64, PullProgress),
+    PullFinished(u64, Result<(), std::io::Error>),
+    DeleteFinished(String, Result<(), std::io::Error>),
+    // SIGINT or SIGTERM from outside; exits through the same path as quitting
+    Terminate,
+}
+
+struct Generation {
+    id: u64,
+    handle: JoinHandle<()>,
+    // the Chat the chunks go to; commands may push other chats after it meanwhile
+    chat_index: usize,
+}
+
+struct PullTask {
+    id: u64,
+    model: String,
+    progress: Option<PullProgress>,
+    handle: JoinHandle<()>,
+}
+
+struct StatusMessage {
+    text: String,
+    is_error: bool,
 }
 
 enum InputMode {
@@ -58,6 +246,9 @@ struct Model {
     size: u64,
     digest: String,
     details: ModelDetails,
+    // filled in from /api/show after listing
+    #[serde(skip)]
+    capabilities: Vec<Capability>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -69,21 +260,44 @@ struct ModelDetails {
     quantization_level: String,
 }
 
-#[derive(Default, Debug)]
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: String,
+}
+
+#[derive(Serialize, Debug)]
+struct ShowRequest {
+    model: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ShowResponse {
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
 struct ChatLog {
     history: Vec<Chat>,
     // lines: Vec<String>,
+    // where a saved conversation was scrolled to; None follows the latest message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scroll: Option<usize>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct Chat {
     author: String,
     content: String,
     // origin_content is usually a single item, but may contain multiple items for OllamaResponse
     origin_content: Vec<ChatType>,
+    // the model named with a leading @model for this one message, instead of the selected one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model_override: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[allow(dead_code)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 enum ChatType {
     UserRequest(String),
     SystemResponse(String),
@@ -95,6 +309,120 @@ enum ChatType {
 struct ChatRequest {
     model: String,
     messages: Vec<MessageChunk>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<ModelOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<Tool>>,
+}
+
+// A function the model may ask to call, described by a JSON schema for its arguments
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Tool {
+    #[serde(rename = "type", default = "Tool::function_type")]
+    kind: String,
+    function: ToolFunction,
+}
+
+impl Tool {
+    fn function_type() -> String {
+        "function".to_string()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ToolFunction {
+    name: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    parameters: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ToolCall {
+    function: ToolCallFunction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct ToolCallFunction {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+// Sampling parameters sent as Ollama's `options` object; unset fields use the model defaults
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+struct ModelOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_k: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_ctx: Option<u32>,
+}
+
+impl ModelOptions {
+    // `key=value` pairs as typed in the regenerate prompt, e.g. "temperature=1.2 seed=7"
+    fn display_pairs(&self) -> String {
+        let mut pairs = vec![];
+        if let Some(temperature) = self.temperature {
+            pairs.push(format!("temperature={}", temperature));
+        }
+        if let Some(top_p) = self.top_p {
+            pairs.push(format!("top_p={}", top_p));
+        }
+        if let Some(top_k) = self.top_k {
+            pairs.push(format!("top_k={}", top_k));
+        }
+        if let Some(seed) = self.seed {
+            pairs.push(format!("seed={}", seed));
+        }
+        if let Some(num_ctx) = self.num_ctx {
+            pairs.push(format!("num_ctx={}", num_ctx));
+        }
+        pairs.join(" ")
+    }
+
+    // The inverse of display_pairs; an option left out of `text` is unset
+    fn parse_pairs(text: &str) -> Result<Self, String> {
+        let mut options = Self::default();
+        for pair in text.split_whitespace() {
+            let Some((key, raw)) = pair.split_once('=') else {
+                return Err(format!("expected key=value, got {}", pair));
+            };
+            options.set(key, raw)?;
+        }
+        Ok(options)
+    }
+
+    // An empty value unsets the option again
+    fn set(&mut self, key: &str, raw: &str) -> Result<(), String> {
+        fn value<T: std::str::FromStr>(key: &str, value: &str) -> Result<Option<T>, String> {
+            if value.is_empty() {
+                return Ok(None);
+            }
+            value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("invalid value for {}: {}", key, value))
+        }
+
+        match key {
+            "temperature" => self.temperature = value(key, raw)?,
+            "top_p" => self.top_p = value(key, raw)?,
+            "top_k" => self.top_k = value(key, raw)?,
+            "seed" => self.seed = value(key, raw)?,
+            "num_ctx" => self.num_ctx = value(key, raw)?,
+            _ => return Err(format!("unknown option {}", key)),
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -103,301 +431,2759 @@ struct ChatResponse {
     created_at: String,
     message: MessageChunk,
     done: bool,
+    // "stop" when the model finished, "length" when it hit num_predict or the context limit
+    done_reason: Option<String>,
+    // only on the final chunk; durations are in nanoseconds
+    eval_count: Option<u64>,
+    eval_duration: Option<u64>,
+    // everything from receiving the request, model load and prompt evaluation included
+    #[serde(default)]
+    total_duration: Option<u64>,
 }
 
-// struct ChatResponseFinal {
-//     model: String,
-//     created_at: String,
-//     done: bool,
-//     total_duration: u64,
-//     load_duration: u64,
-//     prompt_eval_count: u64,
-//     prompt_eval_duration: u64,
-//     eval_count: u64,
-//     eval_duration: u64,
-// }
-
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct MessageChunk {
     role: String,
     content: String,
     images: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+}
+
+// A chat request as the selected API flavor expects it
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum ChatBody<'a> {
+    Ollama(&'a ChatRequest),
+    OpenAI(openai::CompletionRequest<'a>),
+}
+
+#[derive(Serialize, Debug)]
+struct DeleteRequest {
+    model: String,
+}
+
+#[derive(Serialize, Debug)]
+struct PullRequest {
+    model: String,
+    stream: bool,
+}
+
+// One line of the /api/pull progress stream; total/completed are only sent while downloading
+#[derive(Deserialize, Debug, Clone)]
+struct PullProgress {
+    #[serde(default)]
+    status: String,
+    total: Option<u64>,
+    completed: Option<u64>,
+    error: Option<String>,
+}
+
+// How a chat reply is framed on the wire: Ollama's NDJSON, or Server-Sent Events with
+// OpenAI-style delta chunks as some gateways send
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum StreamFormat {
+    #[default]
+    Ndjson,
+    Sse,
 }
 
+// Which API the server speaks; Ollama itself also serves the OpenAI-compatible one under /v1
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum ApiFlavor {
+    #[default]
+    Ollama,
+    OpenAI,
+}
+
+#[derive(Clone)]
 struct OllamaApi {
     base_url: String,
     client: reqwest::Client,
+    stream_format: StreamFormat,
+    flavor: ApiFlavor,
+    // sent as a bearer token, for backends behind authentication
+    api_key: Option<String>,
+    // for the calls that answer at once; chat and pull have no limit past connecting
+    timeout: Duration,
+}
+
+const DEFAULT_HOST: &str = "http://localhost:11434";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+// delays between model list attempts, for a server that is still starting up
+const MODEL_LIST_RETRIES: [Duration; 2] = [Duration::from_millis(250), Duration::from_millis(750)];
+
+// Like ollama itself, a bare "10.0.0.2" means plain http on the default port 11434
+fn parse_host(host: &str) -> Result<String, String> {
+    let host = host.trim_end_matches('/');
+    let host = if host.contains("://") {
+        host.to_string()
+    } else {
+        let (host_port, path) = host.split_at(host.find('/').unwrap_or(host.len()));
+        let has_port = host_port
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()));
+        if has_port {
+            format!("http://{}", host)
+        } else {
+            format!("http://{}:11434{}", host_port, path)
+        }
+    };
+    reqwest::Url::parse(&host).map_err(|error| error.to_string())?;
+    Ok(host)
 }
 
 impl Default for OllamaApi {
     fn default() -> Self {
         Self {
-            base_url: "http://localhost:11434".to_string(),
-            client: reqwest::Client::new(),
+            base_url: DEFAULT_HOST.to_string(),
+            client: reqwest::Client::builder()
+                .connect_timeout(CONNECT_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            stream_format: StreamFormat::default(),
+            flavor: ApiFlavor::default(),
+            api_key: None,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
         }
     }
 }
 
 impl OllamaApi {
-    async fn get_models(&self) -> Result<ModelList, reqwest::Error> {
-        let response = reqwest::get(format!("{}/api/tags", self.base_url)).await;
-        match response {
-            Ok(response) => {
-                let response = response.bytes().await.unwrap();
-                let model_list = serde_json::from_slice::<ModelList>(&response).unwrap();
-                Ok(model_list)
-            }
-            Err(error) => Err(error),
+    // `unix:///path/to/ollama.sock` speaks HTTP over that socket instead of TCP.
+    // Without an explicit proxy, reqwest picks up HTTP_PROXY/HTTPS_PROXY/NO_PROXY itself
+    fn new(host: &str, proxy: Option<&str>) -> Result<Self, String> {
+        let host = parse_host(host)?;
+        if let Some(path) = host.strip_prefix("unix://") {
+            return Self::unix_socket(path);
+        }
+
+        let mut builder = reqwest::Client::builder().connect_timeout(CONNECT_TIMEOUT);
+        if let Some(proxy) = proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|error| format!("invalid proxy {}: {}", proxy, error))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            builder = builder.proxy(proxy);
         }
+        Ok(Self {
+            base_url: host,
+            client: builder.build().map_err(|error| error.to_string())?,
+            ..Self::default()
+        })
     }
 
-    // TODO
-    async fn chat(&self, chat_request: ChatRequest) -> Result<Vec<ChatResponse>, std::io::Error> {
-        let response = self
-            .client
-            .post(format!("{}/api/chat", self.base_url))
-            .body(serde_json::to_string(&chat_request).unwrap())
-            .send()
-            .await;
+    // the host in the URL is only a placeholder, every connection goes to the socket
+    #[cfg(unix)]
+    fn unix_socket(path: &str) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .unix_socket(path)
+            .build()
+            .map_err(|error| error.to_string())?;
+        Ok(Self {
+            base_url: "http://localhost".to_string(),
+            client,
+            ..Self::default()
+        })
+    }
 
-        match response {
-            Ok(response) => {
-                if response.status().is_success() {
-                    let stream = response.bytes_stream();
+    #[cfg(not(unix))]
+    fn unix_socket(_path: &str) -> Result<Self, String> {
+        Err("unix sockets are not supported on this platform".to_string())
+    }
 
-                    let messages: Vec<ChatResponse> = stream
-                        .map(|item| serde_json::from_slice::<ChatResponse>(&item.unwrap()).unwrap())
-                        .collect()
-                        .await;
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
 
-                    return Ok(messages);
-                }
-            }
-            Err(error) => {
-                println!("error: {:?}", error);
+    // Only failures to get through are retried, an error status is the server's answer
+    async fn get_models(&self) -> Result<ModelList, reqwest::Error> {
+        let mut retries = MODEL_LIST_RETRIES.iter();
+        loop {
+            match self.fetch_models().await {
+                Err(error) if error.is_connect() || error.is_timeout() => match retries.next() {
+                    Some(delay) => tokio::time::sleep(*delay).await,
+                    None => return Err(error),
+                },
+                result => return result,
             }
         }
+    }
+
+    async fn fetch_models(&self) -> Result<ModelList, reqwest::Error> {
+        if self.flavor == ApiFlavor::OpenAI {
+            let models: openai::ModelsResponse = self
+                .request(reqwest::Method::GET, "/v1/models")
+                .timeout(self.timeout)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            return Ok(models.into_model_list());
+        }
 
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Failed to chat",
-        ))
+        self.request(reqwest::Method::GET, "/api/tags")
+            .timeout(self.timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
     }
-}
 
-impl Default for ModelInfo {
-    fn default() -> Self {
-        Self {
-            models: ModelList { models: vec![] },
-            selected_model: ListState::default(),
+    // The OpenAI API has nothing like /api/show, so capabilities are guessed from the name there
+    async fn show_model(&self, model: &str) -> Result<ShowResponse, reqwest::Error> {
+        if self.flavor == ApiFlavor::OpenAI {
+            return Ok(ShowResponse {
+                capabilities: vec![],
+            });
         }
+
+        self.request(reqwest::Method::POST, "/api/show")
+            .json(&ShowRequest {
+                model: model.to_string(),
+            })
+            .timeout(self.timeout)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
     }
-}
 
-impl Default for App {
-    fn default() -> Self {
-        Self {
-            exit: false,
-            models_info: ModelInfo::default(),
-            selected_model: Model::default(),
-            ollama_api: OllamaApi::default(),
-            last_chat_area_height: 0,
-            last_chat_area_width: 0,
-            input: String::new(),
-            input_mode: InputMode::Normal,
-            character_index: 0,
-            chat_log: ChatLog::default(),
-            chat_scroll_state: ScrollbarState::new(0).position(0),
-            chat_scroll: 0,
+    // The endpoint and body a chat request is sent as, also what the debug overlay and dry runs show
+    fn chat_body<'a>(&self, chat_request: &'a ChatRequest) -> (&'static str, ChatBody<'a>) {
+        match self.flavor {
+            ApiFlavor::Ollama => ("/api/chat", ChatBody::Ollama(chat_request)),
+            ApiFlavor::OpenAI => (
+                "/v1/chat/completions",
+                ChatBody::OpenAI(openai::CompletionRequest::from(chat_request)),
+            ),
         }
     }
-}
 
-// App logic
-impl App {
-    async fn run(mut self, mut terminal: DefaultTerminal) -> std::io::Result<()> {
-        self.load_models().await;
+    // Sends each response chunk over `events` as it arrives; the final chunk has `done: true`
+    async fn chat(
+        &self,
+        chat_request: &ChatRequest,
+        generation: u64,
+        events: &UnboundedSender<AppEvent>,
+    ) -> Result<(), std::io::Error> {
+        let (endpoint, body) = self.chat_body(chat_request);
+        let response = self
+            .request(reqwest::Method::POST, endpoint)
+            .json(&body)
+            .send()
+            .await
+            .map_err(send_error)?;
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
 
-        while !self.exit {
-            terminal.draw(|frame| self.draw(frame))?;
-            if let Event::Key(key) = event::read()? {
-                self.handle_key(key).await;
+        let send = |chunk: ChatResponse| {
+            let _ = events.send(AppEvent::ChatChunk(generation, chunk));
+            Ok(())
+        };
+        // OpenAI-compatible servers always stream events
+        match (self.flavor, self.stream_format) {
+            (ApiFlavor::Ollama, StreamFormat::Ndjson) => for_each_line(response, send).await,
+            _ => {
+                for_each_sse_event(response, |chunk: CompletionChunk| {
+                    chunk.into_chat_response().map_or(Ok(()), send)
+                })
+                .await
             }
         }
+    }
 
+    async fn delete_model(&self, model: &str) -> Result<(), std::io::Error> {
+        if self.flavor == ApiFlavor::OpenAI {
+            return Err(std::io::Error::other("deleting needs the Ollama API"));
+        }
+        let response = self
+            .request(reqwest::Method::DELETE, "/api/delete")
+            .json(&DeleteRequest {
+                model: model.to_string(),
+            })
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(send_error)?;
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
         Ok(())
     }
 
-    async fn handle_key(&mut self, key: KeyEvent) {
-        if key.kind != KeyEventKind::Press {
-            return;
+    // Streams the newline-delimited progress objects over `progress` until the pull completes
+    async fn pull_model(
+        &self,
+        model: &str,
+        pull: u64,
+        progress: &UnboundedSender<AppEvent>,
+    ) -> Result<(), std::io::Error> {
+        if self.flavor == ApiFlavor::OpenAI {
+            return Err(std::io::Error::other("pulling needs the Ollama API"));
         }
-
-        match self.input_mode {
-            InputMode::Normal => match key.code {
-                KeyCode::Esc => self.exit = true,
-                KeyCode::Char('e') => {
-                    if self.selected_model.name.is_empty() {
-                        return;
-                    }
-                    self.input_mode = InputMode::Editing;
-                }
-                KeyCode::Down => self.models_info.selected_model.select_next(),
-                KeyCode::Up => self.models_info.selected_model.select_previous(),
-                KeyCode::Enter => self.select_model(),
-                _ => {}
-            },
-            InputMode::Editing => match key.code {
-                KeyCode::Enter => {
-                    self.chat_message().await;
-
-                    // TODO: analyze the code
-                    // if self.chat_log.len() > (self.last_chat_area_height - 2) {
-                    //     self.chat_scroll = self.chat_log.len() - (self.last_chat_area_height - 2);
-                    // } else {
-                    //     self.chat_scroll = 0;
-                    // }
-                    // self.chat_scroll_state.last();
-                }
-                KeyCode::Char(c) => self.update_input(c),
-                KeyCode::Backspace => self.delete_input(),
-                KeyCode::Left => self.move_cursor_left(),
-                KeyCode::Right => self.move_cursor_right(),
-                KeyCode::Down => {
-                    // TODO: analyze the code
-                    // let mut clamp: usize = 0;
-                    // if self.chat_log.len() > self.last_chat_area_height - 2 {
-                    //     clamp = self.chat_log.len() - (self.last_chat_area_height - 2) + 1;
-                    // }
-
-                    // self.chat_scroll = self
-                    //     .chat_scroll
-                    //     .saturating_add(1)
-                    //     .clamp(0, clamp.saturating_sub(1));
-                    // self.chat_scroll_state.next();
-                }
-                KeyCode::Up => {
-                    // TODO: analyze the code
-                    // self.chat_scroll = self.chat_scroll.saturating_sub(1);
-                    // self.chat_scroll_state.prev();
-                }
-                KeyCode::Esc => self.input_mode = InputMode::Normal,
-                _ => {}
-            },
+        let response = self
+            .request(reqwest::Method::POST, "/api/pull")
+            .json(&PullRequest {
+                model: model.to_string(),
+                stream: true,
+            })
+            .send()
+            .await
+            .map_err(send_error)?;
+        if !response.status().is_success() {
+            return Err(std::io::Error::other(format!(
+                "pull failed with status {}",
+                response.status()
+            )));
         }
-    }
 
-    fn set_chat_area_size(&mut self, area: Rect) {
-        self.last_chat_area_height = area.height.into();
-        self.last_chat_area_width = area.width.into();
+        for_each_line(response, |pull_progress: PullProgress| {
+            if let Some(error) = pull_progress.error {
+                return Err(std::io::Error::other(error));
+            }
+            let _ = progress.send(AppEvent::PullProgress(pull, pull_progress));
+            Ok(())
+        })
+        .await
     }
+}
 
-    fn select_model(&mut self) {
-        if let Some(selected_model) = self.models_info.selected_model.selected() {
-            self.selected_model = self.models_info.models.models[selected_model].clone();
+// Parses a newline-delimited JSON body one object at a time, stopping at the first error
+async fn for_each_line<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+    mut on_line: impl FnMut(T) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    for_each_raw_line(response, |line| {
+        if line.is_empty() {
+            return Ok(());
         }
-    }
+        on_line(parse_json(line)?)
+    })
+    .await
+}
 
-    async fn chat_message(&mut self) {
-        if self.input.is_empty() {
-            return;
+// Each `data:` line is taken as a whole event, which is all OpenAI-style streams send;
+// `event:` lines, comments and everything after `data: [DONE]` are skipped
+async fn for_each_sse_event<T: serde::de::DeserializeOwned>(
+    response: reqwest::Response,
+    mut on_event: impl FnMut(T) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    let mut done = false;
+    for_each_raw_line(response, |line| {
+        let Some(data) = line.strip_prefix(b"data:") else {
+            return Ok(());
+        };
+        let data = data.trim_ascii();
+        if done || data.is_empty() {
+            return Ok(());
         }
+        if data == b"[DONE]" {
+            done = true;
+            return Ok(());
+        }
+        on_event(parse_json(data)?)
+    })
+    .await
+}
 
-        // TODO: if the input starts with '/', it is a command type
-        let input_chat = Chat {
-            author: "user".to_string(),
-            content: self.input.clone(),
-            origin_content: vec![ChatType::OllamaRequest(ChatRequest {
-                model: self.selected_model.name.clone(),
-                messages: vec![MessageChunk {
-                    role: "user".to_string(),
-                    content: self.input.clone(),
-                    images: None,
-                }],
-            })],
-        };
-        self.update_chat_log_single(input_chat.clone());
-
-        // TODO: async chat, because it is not good, when waiting for the response
-        if let ChatType::OllamaRequest(chat_request) = input_chat.origin_content[0].clone() {
-            self.chat(chat_request).await;
-        } else {
-            eprintln!("Error: input chat type is not OllamaRequest");
+// Lines of the body as they complete, trimmed; the last one doesn't need a newline
+async fn for_each_raw_line(
+    response: reqwest::Response,
+    mut on_line: impl FnMut(&[u8]) -> Result<(), std::io::Error>,
+) -> Result<(), std::io::Error> {
+    let mut stream = response.bytes_stream();
+    let mut buffer: Vec<u8> = vec![];
+    while let Some(item) = stream.next().await {
+        buffer.extend_from_slice(&item.map_err(std::io::Error::other)?);
+
+        // an object may be split across chunks, so only hand out complete lines
+        while let Some(newline) = buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=newline).collect();
+            on_line(line.trim_ascii())?;
         }
+    }
 
-        self.input.clear();
-        self.reset_cursor();
+    if !buffer.is_empty() {
+        on_line(buffer.trim_ascii())?;
     }
+    Ok(())
+}
 
-    fn get_character_index(&self) -> usize {
-        self.input
-            .char_indices()
-            .map(|(i, _)| i)
-            .nth(self.character_index)
-            .unwrap_or(self.input.len())
+fn parse_json<T: serde::de::DeserializeOwned>(line: &[u8]) -> Result<T, std::io::Error> {
+    serde_json::from_slice(line).map_err(|error| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("unexpected response from the server: {}", error),
+        )
+    })
+}
+
+// Rough token estimate (~4 characters per token), good enough to warn before the context fills up
+fn estimate_tokens<'a>(texts: impl IntoIterator<Item = &'a str>) -> usize {
+    texts
+        .into_iter()
+        .map(|text| text.chars().count().div_ceil(4))
+        .sum()
+}
+
+impl Chat {
+    // Only the latest final chunk counts, so a continued reply is no longer truncated
+    fn is_truncated(&self) -> bool {
+        self.origin_content
+            .iter()
+            .rev()
+            .find_map(|origin| match origin {
+                ChatType::OllamaResponse(response) if response.done => {
+                    Some(response.done_reason.as_deref() == Some("length"))
+                }
+                _ => None,
+            })
+            .unwrap_or(false)
     }
 
-    fn move_cursor_left(&mut self) {
-        let cursor_moved_left = self.character_index.saturating_sub(1);
-        self.character_index = self.clamp_cursor(cursor_moved_left);
+    fn is_system_response(&self) -> bool {
+        matches!(
+            self.origin_content.first(),
+            Some(ChatType::SystemResponse(_))
+        )
     }
 
-    fn move_cursor_right(&mut self) {
-        let cursor_moved_right = self.character_index.saturating_add(1);
-        self.character_index = self.clamp_cursor(cursor_moved_right);
+    // Base64 images attached to the user's prompt or returned in the reply
+    fn images(&self) -> Vec<&str> {
+        self.origin_content
+            .iter()
+            .filter_map(|origin| match origin {
+                ChatType::OllamaRequest(request) if self.author == "user" => request
+                    .messages
+                    .last()
+                    .and_then(|message| message.images.as_ref()),
+                ChatType::OllamaResponse(response) => response.message.images.as_ref(),
+                _ => None,
+            })
+            .flatten()
+            .map(String::as_str)
+            .collect()
     }
 
-    fn clamp_cursor(&self, cursor_index: usize) -> usize {
-        cursor_index.clamp(0, self.input.chars().count())
+    // Tool calls the model asked for in this reply
+    fn tool_calls(&self) -> Vec<&ToolCall> {
+        self.origin_content
+            .iter()
+            .filter_map(|origin| match origin {
+                ChatType::OllamaResponse(response) => response.message.tool_calls.as_ref(),
+                _ => None,
+            })
+            .flatten()
+            .collect()
     }
 
-    fn reset_cursor(&mut self) {
-        self.character_index = 0;
+    fn images_request(&self) -> Option<Vec<String>> {
+        let images: Vec<String> = self.images().into_iter().map(str::to_string).collect();
+        (!images.is_empty()).then_some(images)
     }
 
-    fn update_input(&mut self, c: char) {
-        let index = self.get_character_index();
-        self.input.insert(index, c);
-        self.move_cursor_right();
+    // The tool calls as sent back in history, so the model sees what it asked for
+    fn tool_calls_request(&self) -> Option<Vec<ToolCall>> {
+        let tool_calls: Vec<ToolCall> = self.tool_calls().into_iter().cloned().collect();
+        (!tool_calls.is_empty()).then_some(tool_calls)
     }
 
-    fn delete_input(&mut self) {
-        let is_not_cursor_leftmost = self.character_index != 0;
-        if is_not_cursor_leftmost {
-            let current_index = self.character_index;
-            let from_left_to_current_index = current_index - 1;
+    // Generation speed reported with the final chunk of the reply
+    fn tokens_per_second(&self) -> Option<f64> {
+        self.origin_content
+            .iter()
+            .rev()
+            .find_map(|origin| match origin {
+                ChatType::OllamaResponse(ChatResponse {
+                    eval_count: Some(count),
+                    eval_duration: Some(duration),
+                    ..
+                }) if *duration > 0 => Some(*count as f64 / (*duration as f64 / 1e9)),
+                _ => None,
+            })
+    }
 
-            let before_char_to_delete = self.input.chars().take(from_left_to_current_index);
-            let after_char_to_delete = self.input.chars().skip(current_index);
+    // e.g. "42 tokens · 31.2 tok/s · 1.4s", from the chunk that finished the reply
+    fn generation_stats(&self) -> Option<String> {
+        let last = self
+            .origin_content
+            .iter()
+            .rev()
+            .find_map(|origin| match origin {
+                ChatType::OllamaResponse(response) if response.done => Some(response),
+                _ => None,
+            })?;
+        let parts: Vec<String> = [
+            last.eval_count.map(|count| format!("{} tokens", count)),
+            self.tokens_per_second()
+                .map(|tokens_per_second| format!("{:.1} tok/s", tokens_per_second)),
+            last.total_duration
+                .map(|duration| format!("{:.1}s", duration as f64 / 1e9)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
 
-            self.input = before_char_to_delete.chain(after_char_to_delete).collect();
-            self.move_cursor_left();
-        }
+    // Nothing to show: no text, tool calls or images once the reply is over
+    fn is_empty_reply(&self) -> bool {
+        self.author == "assistant"
+            && !self.is_streaming()
+            && self.content.trim().is_empty()
+            && self.tool_calls().is_empty()
+            && self.images().is_empty()
     }
 
-    fn update_chat_log_single(&mut self, chat: Chat, stream: bool) {
-        self.chat_log.history.push(chat);
+    // Set by stop_generation; what arrived until then counts as the whole reply
+    fn is_stopped(&self) -> bool {
+        self.author == "assistant"
+            && matches!(
+                self.origin_content.last(),
+                Some(ChatType::SystemResponse(note)) if note == REPLY_STOPPED
+            )
     }
 
-    fn update_chat_log_multiple(&mut self, chats: Vec<Chat>) {
-        let new_chat = Chat {
-            author: "assistant".to_string(),
-            content: chats.last().unwrap().content.clone(),
-            origin_content: chats
-                .iter()
-                .map(|chat| chat.origin_content.clone())
-                .collect(),
-        };
-        self.chat_log.history.push(new_chat);
+    // Set by finish_reply when the stream stopped without a `done: true` chunk
+    fn ended_early(&self) -> bool {
+        self.author == "assistant"
+            && matches!(
+                self.origin_content.last(),
+                Some(ChatType::SystemResponse(note)) if note == STREAM_ENDED_EARLY
+            )
     }
-}
 
-// UI
-impl App {
-    fn draw(&mut self, frame: &mut Frame) {
-        let [header_area, list_area, footer_area] = Layout::vertical([
+    // An assistant reply is still growing until its latest response chunk says done
+    fn is_streaming(&self) -> bool {
+        self.author == "assistant"
+            && match self.origin_content.last() {
+                Some(ChatType::OllamaResponse(response)) => !response.done,
+                Some(ChatType::OllamaRequest(_)) => true,
+                _ => false,
+            }
+    }
+
+    // Sent, but not a single chunk back yet
+    fn is_waiting(&self) -> bool {
+        self.author == "assistant"
+            && matches!(self.origin_content.last(), Some(ChatType::OllamaRequest(_)))
+    }
+
+    fn end_stream(&mut self) {
+        if self.is_streaming() {
+            self.origin_content
+                .push(ChatType::SystemResponse(STREAM_ENDED_EARLY.to_string()));
+        }
+    }
+}
+
+// Decodes a base64 image into a preview sized for the chat; None if it can't be shown
+fn image_preview(picker: &Picker, data: &str) -> Option<Protocol> {
+    let bytes = BASE64_STANDARD.decode(data).ok()?;
+    let image = image::load_from_memory(&bytes).ok()?;
+    let size = Rect::new(0, 0, IMAGE_PREVIEW_WIDTH, IMAGE_PREVIEW_HEIGHT);
+    picker.new_protocol(image, size, Resize::Fit(None)).ok()
+}
+
+fn render_confirm(frame: &mut Frame, confirm: &Confirm) {
+    let width = (confirm.message.chars().count() as u16 + 4).max(24);
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::vertical([Constraint::Length(4)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let popup = Paragraph::new(vec![
+        Line::from(confirm.message.as_str()),
+        Line::from("y: yes, n: no").dim(),
+    ])
+    .centered()
+    .block(Block::bordered().title("Confirm").fg(Color::Yellow));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+// Layers without a size yet (manifest, verifying) leave the bar empty with just their status
+fn render_pull_progress(frame: &mut Frame, area: Rect, pull: &PullTask) {
+    let (ratio, label) = match &pull.progress {
+        Some(PullProgress {
+            status,
+            total: Some(total),
+            completed,
+            ..
+        }) if *total > 0 => {
+            let completed = completed.unwrap_or(0).min(*total);
+            (
+                completed as f64 / *total as f64,
+                format!(
+                    "{} {}% ({} / {})",
+                    status,
+                    completed * 100 / total,
+                    human_size(completed),
+                    human_size(*total)
+                ),
+            )
+        }
+        Some(progress) => (0.0, progress.status.clone()),
+        None => (0.0, "starting".to_string()),
+    };
+    let gauge = Gauge::default()
+        .gauge_style(Color::Cyan)
+        .use_unicode(true)
+        .ratio(ratio)
+        .label(Span::raw(label).bold());
+    frame.render_widget(gauge, area);
+}
+
+// e.g. "llama · 8.0B · Q4_K_M · 4.9 GB", leaving out whatever the server didn't report
+fn model_summary(model: &Model) -> String {
+    let details = &model.details;
+    let size = (model.size > 0).then(|| human_size(model.size));
+    [
+        Some(details.family.clone()),
+        Some(details.parameter_size.clone()),
+        Some(details.quantization_level.clone()),
+        size,
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" · ")
+}
+
+fn render_model_details(frame: &mut Frame, model: &Model) {
+    let details = &model.details;
+    let family = match &details.families {
+        Some(families) if families.len() > 1 => {
+            format!("{} ({})", details.family, families.join(", "))
+        }
+        _ => details.family.clone(),
+    };
+    let modified = relative_time::relative_time(&model.modified_at, chrono::Utc::now())
+        .unwrap_or_else(|| model.modified_at.clone());
+    // the first 12 characters are what `ollama list` shows as the ID
+    let digest = model.digest.get(..12).unwrap_or(&model.digest);
+    let rows = [
+        ("family", family),
+        ("parameters", details.parameter_size.clone()),
+        ("quantization", details.quantization_level.clone()),
+        ("format", details.format.clone()),
+        ("size", human_size(model.size)),
+        ("modified", modified),
+        ("capabilities", capability::badges(&model.capabilities)),
+        ("digest", digest.to_string()),
+    ];
+
+    let mut lines = vec![Line::from(model.name.as_str()).bold(), Line::default()];
+    lines.extend(
+        rows.into_iter()
+            .map(|(label, value)| Line::from(vec![format!("{:<14}", label).dim(), value.into()])),
+    );
+    lines.push(Line::default());
+    lines.push(Line::from("y: copy full digest, Esc: close").dim());
+
+    let width = lines.iter().map(Line::width).max().unwrap_or(0) as u16 + 4;
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::vertical([Constraint::Length(lines.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let popup = Paragraph::new(lines).block(Block::bordered().title("Model").fg(Color::Cyan));
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+// Asks the terminal to set the clipboard (OSC 52), which also works over SSH; tmux needs set-clipboard
+fn copy_to_clipboard(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", BASE64_STANDARD.encode(text))?;
+    stdout.flush()
+}
+
+// The search query box, with its match count and which options are on
+fn render_search_prompt(frame: &mut Frame, area: Rect, search: &ChatSearch, keymap: &Keymap) {
+    let toggle = |label: &'static str, enabled: bool| {
+        if enabled {
+            Span::raw(label).fg(Color::Cyan)
+        } else {
+            Span::raw(label).dim()
+        }
+    };
+
+    let mut title = vec![
+        Span::raw("Search "),
+        toggle("[Aa]", search.case_sensitive),
+        Span::raw(" "),
+        toggle("[.*]", search.regex),
+    ];
+    match &search.error {
+        Some(error) => title.push(Span::raw(format!(" {} ", error)).fg(Color::Red)),
+        None if search.query.is_empty() => {}
+        None if search.matches.is_empty() => title.push(Span::raw(" no matches ")),
+        None => title.push(Span::raw(format!(
+            " {}/{} ",
+            search.current + 1,
+            search.matches.len()
+        ))),
+    }
+    let hints = if search.editing {
+        " Ctrl+T: case, Ctrl+R: regex, Enter: done, Esc: cancel ".to_string()
+    } else {
+        format!(
+            " {}/{}: next/previous, Esc: clear ",
+            keymap.label(Action::SearchNext),
+            keymap.label(Action::SearchPrevious)
+        )
+    };
+
+    let prompt = Paragraph::new(search.query.as_str()).block(
+        Block::bordered()
+            .title(Line::from(title))
+            .title_bottom(Line::from(hints).dim()),
+    );
+    frame.render_widget(prompt, area);
+}
+
+// Popup just above the input box listing the commands that match what is typed
+fn render_command_completions(frame: &mut Frame, input_area: Rect, completions: &[&CommandInfo]) {
+    if completions.is_empty() {
+        return;
+    }
+
+    let height = (completions.len() as u16 + 2).min(input_area.y);
+    let width = completions
+        .iter()
+        .map(|command| command.usage.len() + command.description.len() + 3)
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let area = Rect::new(
+        input_area.x,
+        input_area.y - height,
+        width.min(input_area.width),
+        height,
+    );
+
+    let lines: Vec<Line> = completions
+        .iter()
+        .map(|command| {
+            Line::from(vec![
+                Span::raw(command.usage).fg(Color::Cyan),
+                Span::raw(format!("  {}", command.description)).dim(),
+            ])
+        })
+        .collect();
+    let popup = Paragraph::new(lines).block(Block::bordered().title("Tab: complete"));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(popup, area);
+}
+
+fn render_template_picker(frame: &mut Frame, templates: &[Template], state: &mut ListState) {
+    let [area] = Layout::horizontal([Constraint::Percentage(60)])
+        .flex(Flex::Center)
+        .areas(frame.area());
+    let [area] = Layout::vertical([Constraint::Length(templates.len() as u16 + 2)])
+        .flex(Flex::Center)
+        .areas(area);
+
+    let items: Vec<ListItem> = templates
+        .iter()
+        .map(|template| {
+            ListItem::new(Line::from(vec![
+                Span::raw(template.name.clone()).bold(),
+                Span::raw(format!("  {}", template.prompt.replace('\n', " "))).dim(),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .highlight_symbol("> ")
+        .highlight_spacing(HighlightSpacing::Always)
+        .block(Block::bordered().title("Templates (Enter: use, Esc: close)"));
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, state);
+}
+
+// Splits content into spans with the search matches highlighted, the current one strongest
+fn highlight_matches<'a>(content: &'a str, matches: &[(Range<usize>, bool)]) -> Vec<Span<'a>> {
+    let mut spans = vec![];
+    let mut position = 0;
+    for (range, is_current) in matches {
+        // matches from before the content changed may no longer line up
+        let Some(matched) = content.get(range.clone()) else {
+            continue;
+        };
+        if range.start < position {
+            continue;
+        }
+        if range.start > position {
+            spans.push(Span::raw(&content[position..range.start]));
+        }
+        // the modifiers are what's left of the highlight once plain mode strips the colors
+        let style = if *is_current {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::UNDERLINED)
+        };
+        spans.push(Span::styled(matched, style));
+        position = range.end;
+    }
+    if position < content.len() {
+        spans.push(Span::raw(&content[position..]));
+    }
+    spans
+}
+
+// Header banners from widest to narrowest; each keeps its lines the same width so they center as a block
+const LOGOS: &[&str] = &[
+    r"       _ _                             _         _                
+  ___ | | | __ _ _ __ ___   __ _      | |_ _   _(_)      _ __ ___ 
+ / _ \| | |/ _` | '_ ` _ \ / _` |_____| __| | | | |_____| '__/ __|
+| (_) | | | (_| | | | | | | (_| |_____| |_| |_| | |_____| |  \__ \
+ \___/|_|_|\__,_|_| |_| |_|\__,_|      \__|\__,_|_|     |_|  |___/",
+    r"       _ _                       
+  ___ | | | __ _ _ __ ___   __ _ 
+ / _ \| | |/ _` | '_ ` _ \ / _` |
+| (_) | | | (_| | | | | | | (_| |
+ \___/|_|_|\__,_|_| |_| |_|\__,_|",
+];
+
+fn logo_width(logo: &str) -> usize {
+    logo.lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+// One message as lines: "author: " in front of the first, or blank when `label` is None, and
+// continuation lines indented to match. The indent is non-breaking spaces so wrapping keeps it.
+fn message_lines<'a>(
+    author: &str,
+    label: Option<&str>,
+    content: &'a str,
+    matches: &[(Range<usize>, bool)],
+) -> Vec<Line<'a>> {
+    let indent = "\u{a0}".repeat(author.chars().count() + 2);
+    let mut lines = vec![];
+    let mut start = 0;
+    for (index, text) in content.split('\n').enumerate() {
+        let end = start + text.len();
+        let prefix = match label {
+            Some(label) if index == 0 => format!("{}: ", label),
+            _ => indent.clone(),
+        };
+        // matches are byte ranges into the whole content, cut to this line and shifted onto it;
+        // a regex match across a newline is highlighted on every line it touches
+        let line_matches: Vec<(Range<usize>, bool)> = matches
+            .iter()
+            .filter(|(range, _)| range.start < end && range.end > start)
+            .map(|(range, is_current)| {
+                (
+                    range.start.max(start) - start..range.end.min(end) - start,
+                    *is_current,
+                )
+            })
+            .collect();
+
+        let mut line = Line::from(Span::styled(prefix, author_style(author)));
+        line.spans.extend(highlight_matches(text, &line_matches));
+        lines.push(line);
+        start = end + 1;
+    }
+    lines
+}
+
+// Only the label is colored, so long replies stay readable in the terminal's own colors
+fn author_style(author: &str) -> Style {
+    match author {
+        "user" => Style::new().fg(Color::Cyan).bold(),
+        "assistant" => Style::new().fg(Color::Green).bold(),
+        "tool" => Style::new().fg(Color::Magenta),
+        _ => Style::new().dim(),
+    }
+}
+
+fn chat_paragraph<'a>(text: impl Into<Text<'a>>, wrap: bool) -> Paragraph<'a> {
+    let paragraph = Paragraph::new(text);
+    if wrap {
+        paragraph.wrap(Wrap { trim: true })
+    } else {
+        paragraph
+    }
+}
+
+fn rendered_line_count(lines: &[Line], width: u16, wrap: bool) -> usize {
+    chat_paragraph(lines.to_vec(), wrap).line_count(width)
+}
+
+// Blinking caret appended to the reply that is still streaming
+fn streaming_indicator(plain: bool) -> &'static str {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    if plain {
+        return [" -", " \\", " |", " /"][(millis / 250 % 4) as usize];
+    }
+    if (millis / 500).is_multiple_of(2) {
+        " ▍"
+    } else {
+        "  "
+    }
+}
+
+// Spinner shown before the first chunk of a reply arrives
+fn waiting_indicator(plain: bool) -> &'static str {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    let frames: &[&str] = if plain {
+        &["-", "\\", "|", "/"]
+    } else {
+        &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"]
+    };
+    frames[(millis / 125 % frames.len() as u128) as usize]
+}
+
+// NO_COLOR (https://no-color.org) or a dumb/legacy console gets ASCII glyphs and no colors
+fn plain_terminal() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let limited_term = matches!(
+        std::env::var("TERM").as_deref(),
+        Ok("dumb" | "linux" | "vt100" | "vt220")
+    );
+    no_color || limited_term
+}
+
+// Drops foreground/background colors but keeps bold, reverse and the like, so highlights still show
+fn strip_colors(frame: &mut Frame) {
+    for cell in frame.buffer_mut().content.iter_mut() {
+        cell.set_fg(Color::Reset).set_bg(Color::Reset);
+    }
+}
+
+// Timeouts get their own kind and a message that says so, reqwest's just names the URL
+fn send_error(error: reqwest::Error) -> std::io::Error {
+    if error.is_timeout() {
+        std::io::Error::new(std::io::ErrorKind::TimedOut, "request timed out")
+    } else {
+        std::io::Error::other(error)
+    }
+}
+
+// Ollama explains failures as `{"error": "..."}`; a 404 keeps NotFound so callers can tell it apart
+async fn error_from_response(response: reqwest::Response) -> std::io::Error {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<ErrorResponse>(&body)
+        .map(|error| error.error)
+        .unwrap_or(body);
+    let kind = if status == reqwest::StatusCode::NOT_FOUND {
+        std::io::ErrorKind::NotFound
+    } else {
+        std::io::ErrorKind::Other
+    };
+    std::io::Error::new(kind, format!("{}: {}", status, message))
+}
+
+// Character ranges of the input's rows: split at newlines, then wrapped every `width` characters.
+// A full row gets an empty one after it so the cursor has somewhere to go at the end.
+fn input_rows(input: &str, width: usize) -> Vec<Range<usize>> {
+    let width = width.max(1);
+    let mut rows = vec![];
+    let mut start = 0;
+    for line in input.split('\n') {
+        let length = line.chars().count();
+        let mut row_start = start;
+        loop {
+            let row_end = (row_start + width).min(start + length);
+            rows.push(row_start..row_end);
+            if row_end - row_start < width {
+                break;
+            }
+            row_start = row_end;
+        }
+        // skip over the newline itself
+        start += length + 1;
+    }
+    rows
+}
+
+// Row and column of a character index; at a wrap point the cursor belongs to the next row
+fn input_cursor(rows: &[Range<usize>], character_index: usize) -> (usize, usize) {
+    let row = rows
+        .iter()
+        .rposition(|row| row.start <= character_index)
+        .unwrap_or(0);
+    let column = character_index - rows.get(row).map_or(0, |row| row.start);
+    (row, column)
+}
+
+// Decimal units, matching what `ollama list` prints
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+impl Default for ModelInfo {
+    fn default() -> Self {
+        Self {
+            models: ModelList { models: vec![] },
+            selected_model: ListState::default(),
+        }
+    }
+}
+
+impl Default for App {
+    fn default() -> Self {
+        let (events_tx, events_rx) = mpsc::unbounded_channel();
+        Self {
+            exit: false,
+            models_info: ModelInfo::default(),
+            selected_model: Model::default(),
+            ollama_api: OllamaApi::default(),
+            config: Config::default(),
+            config_loaded: false,
+            model_settings: ModelSettings::default(),
+            last_chat_area_height: 0,
+            last_chat_area_width: 0,
+            input: String::new(),
+            input_mode: InputMode::Normal,
+            character_index: 0,
+            chat_log: ChatLog::default(),
+            status_message: None,
+            chat_scroll_state: ScrollbarState::new(0).position(0),
+            chat_scroll: 0,
+            chat_line_count: 0,
+            follow_tail: true,
+            seen_line_count: None,
+            chat_wrap: true,
+            chat_scroll_x: 0,
+            message_line_offsets: vec![],
+            search: None,
+            saved_history: None,
+            events_tx,
+            events_rx,
+            pull: None,
+            confirm: None,
+            template_picker: None,
+            template_fill: None,
+            debug_overlay: false,
+            debug_scroll: 0,
+            focused: true,
+            keymap: Keymap::default(),
+            connected: None,
+            transcript: None,
+            plain: false,
+            generation: None,
+            last_task_id: 0,
+            one_shot: false,
+            dry_run: false,
+            regenerate_input: None,
+            system_prompt_input: None,
+            model_number: None,
+            model_details: None,
+            composing: false,
+            wanted_model: None,
+            pull_wanted_model: false,
+            host: None,
+            system_prompt: None,
+            pending_images: vec![],
+            image_picker: None,
+            image_previews: HashMap::new(),
+        }
+    }
+}
+
+// App logic
+impl App {
+    async fn run(mut self, mut terminal: DefaultTerminal) -> std::io::Result<()> {
+        listen_for_signals(self.events_tx.clone());
+        self.load_config();
+        self.load_models().await;
+        self.select_wanted_model();
+
+        let mut terminal_events = EventStream::new();
+        // redraws spinners and progress while nothing else is happening
+        let mut tick = tokio::time::interval(Duration::from_millis(125));
+        while !self.exit {
+            terminal.draw(|frame| self.draw(frame))?;
+            tokio::select! {
+                event = terminal_events.next() => match event {
+                    Some(Ok(Event::Key(key))) => self.handle_key(key).await,
+                    Some(Ok(Event::FocusGained)) => self.focused = true,
+                    Some(Ok(Event::FocusLost)) => self.focused = false,
+                    Some(Err(error)) => return Err(error),
+                    _ => {}
+                },
+                Some(event) = self.events_rx.recv() => self.handle_app_event(event).await,
+                _ = tick.tick(), if self.generation.is_some() || self.pull.is_some() => {}
+            }
+        }
+
+        self.stop_tasks().await;
+        Ok(())
+    }
+
+    // Aborts background work and waits for it, so nothing is still writing once the terminal is restored
+    async fn stop_tasks(&mut self) {
+        if let Some(generation) = self.generation.take() {
+            generation.handle.abort();
+            let _ = generation.handle.await;
+        }
+        if let Some(pull) = self.pull.take() {
+            pull.handle.abort();
+            let _ = pull.handle.await;
+        }
+    }
+
+    async fn handle_app_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Terminate => self.exit = true,
+            AppEvent::ChatChunk(id, chunk) => {
+                if self.is_current_generation(id) {
+                    self.append_chunk(chunk);
+                }
+            }
+            AppEvent::ChatFinished(id, result) => {
+                if self.is_current_generation(id) {
+                    self.finish_generation(result).await;
+                }
+            }
+            AppEvent::PullProgress(id, progress) => {
+                // progress queued before a cancel may still arrive, drop it
+                if let Some(pull) = self.pull.as_mut().filter(|pull| pull.id == id) {
+                    pull.progress = Some(progress);
+                }
+            }
+            AppEvent::PullFinished(id, result) => {
+                let Some(pull) = self.pull.take_if(|pull| pull.id == id) else {
+                    return;
+                };
+                let wanted = self.wanted_model.take_if(|wanted| *wanted == pull.model);
+                match result {
+                    Ok(()) => {
+                        self.set_status(format!("Pulled {}", pull.model));
+                        self.notify();
+                        self.load_models().await;
+                        if let Some(wanted) = wanted {
+                            self.select_model_named(&wanted);
+                        }
+                    }
+                    Err(error) => {
+                        self.set_status_error(format!("Pull of {} failed: {}", pull.model, error))
+                    }
+                }
+            }
+            AppEvent::DeleteFinished(model, result) => match result {
+                Ok(()) => {
+                    if self.selected_model.name == model {
+                        self.selected_model = Model::default();
+                        self.model_settings = ModelSettings::default();
+                        self.input_mode = InputMode::Normal;
+                    }
+                    self.load_models().await;
+                    self.set_status(format!("Deleted {}", model));
+                }
+                Err(error) => {
+                    self.set_status_error(format!("Deleting {} failed: {}", model, error))
+                }
+            },
+        }
+    }
+
+    async fn handle_key(&mut self, key: KeyEvent) {
+        if key.kind != KeyEventKind::Press {
+            return;
+        }
+        // an error stays up until the next key, whatever that key goes on to do
+        if self
+            .status_message
+            .as_ref()
+            .is_some_and(|status| status.is_error)
+        {
+            self.status_message = None;
+        }
+
+        if let Some(confirm) = self.confirm.take() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => self.run_confirmed(confirm.action),
+                KeyCode::Char('n') | KeyCode::Esc => {}
+                // anything else leaves the question open
+                _ => self.confirm = Some(confirm),
+            }
+            return;
+        }
+
+        if self.template_picker.is_some() {
+            self.handle_template_picker_key(key);
+            return;
+        }
+
+        if self.debug_overlay {
+            match key.code {
+                KeyCode::Esc => self.debug_overlay = false,
+                _ if self.keymap.action(&key) == Some(Action::Debug) => self.debug_overlay = false,
+                KeyCode::Down => self.debug_scroll = self.debug_scroll.saturating_add(1),
+                KeyCode::Up => self.debug_scroll = self.debug_scroll.saturating_sub(1),
+                KeyCode::PageDown => self.debug_scroll = self.debug_scroll.saturating_add(10),
+                KeyCode::PageUp => self.debug_scroll = self.debug_scroll.saturating_sub(10),
+                KeyCode::Home => self.debug_scroll = 0,
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(index) = self.model_details {
+            match key.code {
+                KeyCode::Char('y') => self.copy_digest(index),
+                KeyCode::Esc => self.model_details = None,
+                _ if self.keymap.action(&key) == Some(Action::ModelDetails) => {
+                    self.model_details = None
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        // Ctrl+C too, since that's the reflex for a runaway reply
+        let stop_key = key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL));
+        if stop_key && self.generation.is_some() {
+            self.stop_generation();
+            return;
+        }
+
+        // only when Esc has nothing else to close, so dropping a draft or a search keeps the download
+        if key.code == KeyCode::Esc
+            && self.pull.is_some()
+            && matches!(self.input_mode, InputMode::Normal)
+            && self.search.is_none()
+        {
+            self.cancel_pull();
+            return;
+        }
+
+        if self.search.as_ref().is_some_and(|search| search.editing) {
+            self.handle_search_key(key);
+            return;
+        }
+
+        match self.input_mode {
+            InputMode::Normal => {
+                let Some(action) = self.keymap.action(&key) else {
+                    if let KeyCode::Char(digit @ '0'..='9') = key.code {
+                        self.type_model_number(digit);
+                    }
+                    return;
+                };
+                match action {
+                    Action::Quit if self.search.is_some() => self.search = None,
+                    Action::Quit => self.exit = true,
+                    Action::Search => self.start_search(),
+                    Action::SearchNext if self.search.is_some() => {
+                        if let Some(search) = &mut self.search {
+                            search.next();
+                        }
+                        self.jump_to_search_match();
+                    }
+                    Action::SearchPrevious if self.search.is_some() => {
+                        if let Some(search) = &mut self.search {
+                            search.previous();
+                        }
+                        self.jump_to_search_match();
+                    }
+                    Action::Edit => {
+                        if self.selected_model.name.is_empty() {
+                            return;
+                        }
+                        self.input_mode = InputMode::Editing;
+                    }
+                    Action::ModelNext => self.models_info.selected_model.select_next(),
+                    Action::ModelPrevious => self.models_info.selected_model.select_previous(),
+                    Action::SelectModel => self.select_model(),
+                    Action::ModelDetails => {
+                        self.model_details = self.models_info.selected_model.selected()
+                    }
+                    Action::Continue => self.continue_response(),
+                    Action::Regenerate => self.start_regenerate(),
+                    Action::SystemPrompt => self.start_system_prompt_edit(),
+                    Action::Favorite => self.toggle_favorite(),
+                    Action::CopyReply => self.copy_last_reply(),
+                    Action::ScrollBottom => self.scroll_to_bottom(),
+                    Action::ToggleWrap => self.toggle_chat_wrap(),
+                    Action::Templates => self.open_template_picker(),
+                    Action::Debug => {
+                        self.debug_overlay = true;
+                        self.debug_scroll = 0;
+                    }
+                    Action::ScrollLeft if !self.chat_wrap => {
+                        self.chat_scroll_x =
+                            self.chat_scroll_x.saturating_sub(HORIZONTAL_SCROLL_STEP)
+                    }
+                    // clamped against the widest line in render_chat
+                    Action::ScrollRight if !self.chat_wrap => {
+                        self.chat_scroll_x =
+                            self.chat_scroll_x.saturating_add(HORIZONTAL_SCROLL_STEP)
+                    }
+                    _ => {}
+                }
+            }
+            InputMode::Editing => match key.code {
+                KeyCode::Enter if self.template_fill.is_some() => self.submit_template_value(),
+                KeyCode::Esc if self.template_fill.is_some() => self.cancel_template_fill(),
+                KeyCode::Enter if self.regenerate_input.is_some() => self.submit_regenerate(),
+                KeyCode::Esc if self.regenerate_input.is_some() => self.cancel_regenerate(),
+                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.composing = !self.composing
+                }
+                KeyCode::Char('s')
+                    if self.composing && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    self.composing = false;
+                    if self.system_prompt_input.is_some() {
+                        self.submit_system_prompt();
+                    } else {
+                        self.chat_message();
+                    }
+                }
+                KeyCode::Enter if self.composing => self.update_input('\n'),
+                // Shift+Enter only arrives apart from Enter where the terminal reports modifiers
+                KeyCode::Enter
+                    if key
+                        .modifiers
+                        .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+                {
+                    self.update_input('\n')
+                }
+                KeyCode::Esc if self.composing => self.composing = false,
+                KeyCode::Enter if self.system_prompt_input.is_some() => self.submit_system_prompt(),
+                KeyCode::Esc if self.system_prompt_input.is_some() => self.cancel_system_prompt(),
+                KeyCode::Enter => self.chat_message(),
+                KeyCode::Tab => {
+                    if let Some(completed) = command::complete(&self.input) {
+                        self.set_input(completed);
+                    }
+                }
+                // unbound Ctrl chords, like a Ctrl+C just after a reply ends, type nothing
+                KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.update_input(c)
+                }
+                KeyCode::Backspace => self.delete_input(),
+                KeyCode::Left => self.move_cursor_left(),
+                KeyCode::Right => self.move_cursor_right(),
+                KeyCode::Down => self.scroll_chat_down(1),
+                KeyCode::Up => self.scroll_chat_up(1),
+                KeyCode::PageDown => self.scroll_chat_down(self.chat_page()),
+                KeyCode::PageUp => self.scroll_chat_up(self.chat_page()),
+                KeyCode::End => self.scroll_to_bottom(),
+                KeyCode::Esc => self.input_mode = InputMode::Normal,
+                _ => {}
+            },
+        }
+    }
+
+    fn start_search(&mut self) {
+        self.search = Some(ChatSearch::new(self.chat_scroll, self.follow_tail));
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => {
+                // cancelling puts the view back where it was before searching
+                self.chat_scroll = search.previous_scroll;
+                self.follow_tail = search.previous_follow_tail;
+                self.search = None;
+                return;
+            }
+            KeyCode::Enter => {
+                search.editing = false;
+                return;
+            }
+            KeyCode::Char('t') if ctrl => search.case_sensitive = !search.case_sensitive,
+            KeyCode::Char('r') if ctrl => search.regex = !search.regex,
+            KeyCode::Char(c) if !ctrl => search.query.push(c),
+            KeyCode::Backspace => {
+                search.query.pop();
+            }
+            _ => return,
+        }
+
+        search.update(&self.chat_log.history);
+        self.jump_to_search_match();
+    }
+
+    // Scrolls so the line holding the current match is at the top of the chat
+    fn jump_to_search_match(&mut self) {
+        let Some(found) = self
+            .search
+            .as_ref()
+            .and_then(|search| search.current_match())
+        else {
+            return;
+        };
+        let Some(chat) = self.chat_log.history.get(found.chat_index) else {
+            return;
+        };
+        let Some(message_offset) = self.message_line_offsets.get(found.chat_index) else {
+            return;
+        };
+
+        // the label doesn't change the layout, only the indent does, so it can always be shown here
+        let Some(before_match) = chat.content.get(..found.range.start) else {
+            return;
+        };
+        let before_match = message_lines(&chat.author, Some(&chat.author), before_match, &[]);
+        let inner_width = self.last_chat_area_width.saturating_sub(2) as u16;
+        let row = rendered_line_count(&before_match, inner_width, self.chat_wrap).saturating_sub(1);
+
+        self.chat_scroll = (message_offset + row).min(self.max_chat_scroll());
+        self.follow_tail = false;
+    }
+
+    fn open_template_picker(&mut self) {
+        if self.selected_model.name.is_empty() {
+            self.set_status_error("Select a model before using a template".to_string());
+            return;
+        }
+        if self.config.templates.is_empty() {
+            self.set_status_error("No templates configured ([[templates]] in config)".to_string());
+            return;
+        }
+
+        self.template_picker = Some(ListState::default().with_selected(Some(0)));
+    }
+
+    fn handle_template_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.template_picker else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Down => picker.select_next(),
+            KeyCode::Up => picker.select_previous(),
+            KeyCode::Enter => {
+                let template = picker
+                    .selected()
+                    .and_then(|index| self.config.templates.get(index))
+                    .cloned();
+                self.template_picker = None;
+                if let Some(template) = template {
+                    self.start_template(template);
+                }
+            }
+            KeyCode::Esc => self.template_picker = None,
+            _ => {}
+        }
+    }
+
+    fn start_template(&mut self, template: Template) {
+        self.input_mode = InputMode::Editing;
+
+        let placeholders = template.placeholders();
+        if placeholders.is_empty() {
+            self.set_input(template.prompt);
+            return;
+        }
+
+        let previous_input = std::mem::take(&mut self.input);
+        self.reset_cursor();
+        self.template_fill = Some(TemplateFill {
+            template,
+            placeholders,
+            values: vec![],
+            previous_input,
+        });
+    }
+
+    fn submit_template_value(&mut self) {
+        let Some(fill) = &mut self.template_fill else {
+            return;
+        };
+
+        let placeholder = fill.placeholders[fill.values.len()].clone();
+        fill.values
+            .push((placeholder, std::mem::take(&mut self.input)));
+        self.character_index = 0;
+
+        if fill.values.len() == fill.placeholders.len() {
+            let prompt = fill.template.fill(&fill.values);
+            self.template_fill = None;
+            self.set_input(prompt);
+        }
+    }
+
+    fn cancel_template_fill(&mut self) {
+        if let Some(fill) = self.template_fill.take() {
+            self.set_input(fill.previous_input);
+        }
+    }
+
+    // Replaces the input buffer and puts the cursor at its end
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+        self.character_index = self.input.chars().count();
+    }
+
+    fn toggle_chat_wrap(&mut self) {
+        self.chat_wrap = !self.chat_wrap;
+        self.chat_scroll_x = 0;
+    }
+
+    // The offset itself is recomputed in render_chat once the wrapped height is known
+    fn scroll_to_bottom(&mut self) {
+        self.follow_tail = true;
+    }
+
+    // chat_line_count is the wrapped height from the last draw, so this matches the screen
+    fn max_chat_scroll(&self) -> usize {
+        self.chat_line_count.saturating_sub(self.chat_page())
+    }
+
+    // lines inside the chat borders
+    fn chat_page(&self) -> usize {
+        self.last_chat_area_height.saturating_sub(2)
+    }
+
+    // Reaching the bottom picks the tail back up, so new output keeps it in view again
+    fn scroll_chat_down(&mut self, lines: usize) {
+        let max_scroll = self.max_chat_scroll();
+        self.chat_scroll = self.chat_scroll.saturating_add(lines).min(max_scroll);
+        self.follow_tail = self.chat_scroll == max_scroll;
+    }
+
+    fn scroll_chat_up(&mut self, lines: usize) {
+        self.chat_scroll = self.chat_scroll.saturating_sub(lines);
+        self.follow_tail = false;
+    }
+
+    fn set_chat_area_size(&mut self, area: Rect) {
+        self.last_chat_area_height = area.height.into();
+        self.last_chat_area_width = area.width.into();
+    }
+
+    fn select_model(&mut self) {
+        if let Some(selected_model) = self.models_info.selected_model.selected() {
+            self.remember_model_settings();
+            self.selected_model = self.models_info.models.models[selected_model].clone();
+            self.model_settings = self.config.model_settings(&self.selected_model.name);
+        }
+    }
+
+    // "llama3.2" also finds "llama3.2:latest", the way ollama resolves names
+    fn find_model(&self, name: &str) -> Option<usize> {
+        let latest = format!("{}:latest", name);
+        self.models_info
+            .models
+            .models
+            .iter()
+            .position(|model| model.name == name || model.name == latest)
+    }
+
+    fn select_model_named(&mut self, name: &str) -> bool {
+        let Some(index) = self.find_model(name) else {
+            return false;
+        };
+        self.models_info.selected_model.select(Some(index));
+        self.select_model();
+        true
+    }
+
+    fn copy_digest(&mut self, index: usize) {
+        let Some(model) = self.models_info.models.models.get(index) else {
+            return;
+        };
+        let digest = model.digest.clone();
+        match copy_to_clipboard(&digest) {
+            Ok(()) => self.set_status(format!("Copied digest {}", digest)),
+            Err(error) => self.set_status_error(format!("Can't copy the digest: {}", error)),
+        }
+    }
+
+    fn last_reply(&self) -> Option<&Chat> {
+        self.chat_log
+            .history
+            .iter()
+            .rev()
+            .find(|chat| chat.author == "assistant" && !chat.content.is_empty())
+    }
+
+    // OSC 52 like the digest, terminals without it just ignore the sequence
+    fn copy_last_reply(&mut self) {
+        let Some(reply) = self.last_reply() else {
+            self.set_status_error("No reply to copy yet".to_string());
+            return;
+        };
+        let content = reply.content.clone();
+        // OSC 52 has no reply, so whether the terminal took it can't be known from here
+        match copy_to_clipboard(&content) {
+            Ok(()) => self.push_system_response(format!(
+                "Sent the last reply ({} characters) to the terminal clipboard via OSC 52; \
+                 terminals without OSC 52 support, or tmux without set-clipboard, ignore it",
+                content.chars().count()
+            )),
+            Err(error) => self.push_system_response(format!("Can't copy the reply: {}", error)),
+        }
+    }
+
+    fn select_wanted_model(&mut self) {
+        let Some(model) = self.wanted_model.take() else {
+            return;
+        };
+        if self.select_model_named(&model) {
+            return;
+        }
+        if self.pull_wanted_model {
+            self.wanted_model = Some(model.clone());
+            self.pull_model(&model);
+        } else {
+            self.ask_confirm(
+                format!("{} is not installed. Pull it?", model),
+                ConfirmAction::PullModel(model),
+            );
+        }
+    }
+
+    // Stores the active settings under the current model so they come back when it is reselected
+    fn remember_model_settings(&mut self) {
+        if self.selected_model.name.is_empty() {
+            return;
+        }
+
+        if self.config.model_settings(&self.selected_model.name) == self.model_settings {
+            return;
+        }
+        self.config
+            .set_model_settings(&self.selected_model.name, self.model_settings.clone());
+        self.save_config();
+    }
+
+    // Rewrites config.toml, unless it failed to load and would be replaced by the defaults
+    fn save_config(&mut self) -> bool {
+        if !self.config_loaded {
+            self.set_status_error("Not saving settings, the config file didn't load".to_string());
+            return false;
+        }
+        match self.config.save() {
+            Ok(()) => true,
+            Err(error) => {
+                self.set_status_error(format!("Error saving config: {}", error));
+                false
+            }
+        }
+    }
+
+    fn load_config(&mut self) {
+        match Config::load() {
+            Ok(config) => {
+                self.config = config;
+                self.config_loaded = true;
+            }
+            Err(error) => {
+                self.config_loaded = false;
+                self.set_status_error(format!("Error loading config: {}", error));
+            }
+        }
+        let host = self
+            .host
+            .as_deref()
+            .or(self.config.host.as_deref())
+            .unwrap_or(DEFAULT_HOST);
+        match OllamaApi::new(host, self.config.proxy.as_deref()) {
+            Ok(ollama_api) => {
+                self.ollama_api = OllamaApi {
+                    timeout: self
+                        .config
+                        .request_timeout
+                        .map_or(DEFAULT_REQUEST_TIMEOUT, Duration::from_secs),
+                    stream_format: self.config.stream_format,
+                    flavor: self.config.api,
+                    api_key: self.config.api_key.clone(),
+                    ..ollama_api
+                }
+            }
+            Err(error) => self.set_status_error(format!("Can't use host {}: {}", host, error)),
+        }
+        self.plain |= self.config.plain;
+        self.transcript = None;
+        if self.config.transcript {
+            match self.config.transcript_path() {
+                Some(path) => match Transcript::open(&path) {
+                    Ok(transcript) => self.transcript = Some(transcript),
+                    Err(error) => self.set_status_error(format!(
+                        "Can't open transcript {}: {}",
+                        path.display(),
+                        error
+                    )),
+                },
+                None => self.set_status_error("No path for the transcript".to_string()),
+            }
+        }
+        // a bad [keybindings] section falls back to the defaults rather than leaving keys dead
+        match Keymap::new(&self.config.keybindings) {
+            Ok(keymap) => self.keymap = keymap,
+            Err(error) => self.set_status_error(format!("Keybindings ignored: {}", error)),
+        }
+    }
+
+    fn toggle_favorite(&mut self) {
+        let Some(index) = self.models_info.selected_model.selected() else {
+            return;
+        };
+        let Some(model) = self.models_info.models.models.get(index) else {
+            return;
+        };
+        let name = model.name.clone();
+
+        // favorites only live in the config, so without one to save into nothing changes
+        if !self.config_loaded {
+            self.set_status_error(
+                "Can't change favorites, the config file didn't load".to_string(),
+            );
+            return;
+        }
+        self.config.toggle_favorite(&name);
+        self.save_config();
+
+        // keep the cursor on the toggled model after it moves between groups
+        self.sort_models();
+        let index = self
+            .models_info
+            .models
+            .models
+            .iter()
+            .position(|model| model.name == name);
+        self.models_info.selected_model.select(index);
+    }
+
+    // Favorites float to the top; the sort is stable so each group keeps its order
+    fn sort_models(&mut self) {
+        let config = &self.config;
+        self.models_info
+            .models
+            .models
+            .sort_by_key(|model| !config.is_favorite(&model.name));
+    }
+
+    fn chat_message(&mut self) {
+        if self.input.is_empty() {
+            return;
+        }
+
+        let input = self.input.clone();
+        if let Some(command) = input.strip_prefix('/') {
+            self.run_command(command);
+        } else if self.still_generating() {
+            // keep the typed message for when the reply is done
+            return;
+        } else if self.pending_tool_call().is_some() {
+            self.send_tool_result(input);
+        } else {
+            let (model_override, prompt) = match self.split_model_override(&input) {
+                Ok(split) => split,
+                Err(error) => {
+                    self.set_status_error(error);
+                    return;
+                }
+            };
+            // the prompt stays in the input until the large-prompt question is answered
+            let tokens = self.context_tokens(&prompt);
+            if tokens > self.large_prompt_tokens() {
+                self.ask_confirm(
+                    format!("This prompt is large (~{} tokens). Send anyway?", tokens),
+                    ConfirmAction::SendPrompt(prompt, model_override),
+                );
+                return;
+            }
+            self.send_prompt(prompt, model_override);
+        }
+
+        self.input.clear();
+        self.reset_cursor();
+    }
+
+    // "@qwen2.5 translate this" sends just that message to an installed qwen2.5
+    fn split_model_override(&self, input: &str) -> Result<(Option<String>, String), String> {
+        let Some((name, prompt)) = input
+            .strip_prefix('@')
+            .and_then(|rest| rest.split_once(char::is_whitespace))
+        else {
+            return Ok((None, input.to_string()));
+        };
+        let prompt = prompt.trim_start();
+        if name.is_empty() || prompt.is_empty() {
+            return Ok((None, input.to_string()));
+        }
+        match self.find_model(name) {
+            Some(index) => Ok((
+                Some(self.models_info.models.models[index].name.clone()),
+                prompt.to_string(),
+            )),
+            None => Err(format!("No model named {} is installed", name)),
+        }
+    }
+
+    fn run_command(&mut self, command: &str) {
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map(|(name, argument)| (name, argument.trim()))
+            .unwrap_or((command, ""));
+
+        match name {
+            "import" => self.import_conversation(argument),
+            "export" => self.export_conversation(argument),
+            "image" => self.attach_image(argument),
+            "save" => self.save_conversation(argument),
+            "load" => self.load_conversation(argument),
+            "context" => self.set_context_turns(argument),
+            "numctx" => self.set_num_ctx(argument),
+            "set" => self.set_model_option(argument),
+            "pull" => self.pull_model(argument),
+            "delete" => self.confirm_delete_model(argument),
+            "retry" => self.retry_last_request(),
+            "oneshot" => self.toggle_one_shot(),
+            "dryrun" => self.toggle_dry_run(),
+            "clear" if self.chat_log.history.is_empty() || self.is_saved() => self.clear_chat(),
+            "clear" => self.ask_confirm(
+                "Clear the whole conversation?".to_string(),
+                ConfirmAction::ClearChat,
+            ),
+            "model" => self.switch_model(argument),
+            "system" => self.set_session_system_prompt(argument),
+            "help" => self.push_system_response(command::help()),
+            "cancel" => self.decline_tool_call(),
+            "quit" => self.exit = true,
+            _ => self.push_system_response(format!("Unknown command: /{}", name)),
+        }
+    }
+
+    fn switch_model(&mut self, name: &str) {
+        if name.is_empty() {
+            let current = if self.selected_model.name.is_empty() {
+                "No model selected".to_string()
+            } else {
+                format!("Using {}", self.selected_model.name)
+            };
+            self.push_system_response(current);
+        } else if self.select_model_named(name) {
+            self.push_system_response(format!("Switched to {}", self.selected_model.name));
+        } else {
+            self.push_system_response(format!("No model named {} is installed", name));
+        }
+    }
+
+    fn toggle_one_shot(&mut self) {
+        self.one_shot = !self.one_shot;
+        self.set_status(if self.one_shot {
+            "One-shot: messages are sent without history".to_string()
+        } else {
+            format!("Sending history again ({})", self.context_description())
+        });
+    }
+
+    // Digits typed in quick succession add up to one number; past the list it starts over
+    fn type_model_number(&mut self, digit: char) {
+        let mut typed = match self.model_number.take() {
+            Some((typed, at)) if at.elapsed() < MODEL_NUMBER_TIMEOUT => typed,
+            _ => String::new(),
+        };
+        typed.push(digit);
+
+        let count = self.models_info.models.models.len();
+        let in_list = |typed: &str| {
+            typed
+                .parse::<usize>()
+                .ok()
+                .filter(|number| (1..=count).contains(number))
+        };
+        let number = match in_list(&typed) {
+            Some(number) => Some(number),
+            None => {
+                typed = digit.to_string();
+                in_list(&typed)
+            }
+        };
+        if let Some(number) = number {
+            self.models_info.selected_model.select(Some(number - 1));
+        }
+        self.model_number = Some((typed, Instant::now()));
+    }
+
+    fn toggle_dry_run(&mut self) {
+        self.dry_run = !self.dry_run;
+        self.set_status(if self.dry_run {
+            "Dry run: requests are shown instead of sent".to_string()
+        } else {
+            "Requests are sent again".to_string()
+        });
+    }
+
+    // Resends the stored request unchanged; whatever came back for it before is dropped first
+    fn retry_last_request(&mut self) {
+        if self.still_generating() {
+            return;
+        }
+        let last_request = self
+            .chat_log
+            .history
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, chat)| chat.author != "assistant")
+            .find_map(|(index, chat)| {
+                chat.origin_content.iter().find_map(|origin| match origin {
+                    ChatType::OllamaRequest(request) => Some((index, request.clone())),
+                    _ => None,
+                })
+            });
+        let Some((index, chat_request)) = last_request else {
+            self.set_status_error("Nothing to retry".to_string());
+            return;
+        };
+
+        self.chat_log.history.truncate(index + 1);
+        self.set_status(format!("Retrying the request to {}", chat_request.model));
+        self.chat(chat_request);
+    }
+
+    // Without a configured threshold, warn once the request would overflow the context window
+    fn large_prompt_tokens(&self) -> usize {
+        self.config
+            .large_prompt_tokens
+            .unwrap_or_else(|| self.context_limit())
+    }
+
+    fn ask_confirm(&mut self, message: String, action: ConfirmAction) {
+        self.confirm = Some(Confirm { message, action });
+    }
+
+    fn run_confirmed(&mut self, action: ConfirmAction) {
+        match action {
+            ConfirmAction::ClearChat => self.clear_chat(),
+            // two replies streaming at once would interleave in the chat, whatever started the first
+            ConfirmAction::SendPrompt(..) if self.still_generating() => {}
+            ConfirmAction::SendPrompt(prompt, model_override) => {
+                self.input.clear();
+                self.reset_cursor();
+                self.send_prompt(prompt, model_override);
+            }
+            ConfirmAction::PullModel(model) => {
+                self.wanted_model = Some(model.clone());
+                self.pull_model(&model);
+            }
+            ConfirmAction::DeleteModel(model) => self.delete_model(model),
+        }
+    }
+
+    fn clear_chat(&mut self) {
+        if let Some(generation) = self.generation.take() {
+            generation.handle.abort();
+        }
+        self.chat_log.history.clear();
+        self.image_previews.clear();
+        self.search = None;
+        self.chat_scroll = 0;
+        self.follow_tail = true;
+        self.set_status("Chat cleared".to_string());
+    }
+
+    fn pull_model(&mut self, model: &str) {
+        if model.is_empty() {
+            self.set_status_error("Usage: /pull <model>".to_string());
+            return;
+        }
+        if let Some(pull) = &self.pull {
+            self.set_status_error(format!("Already pulling {}", pull.model));
+            return;
+        }
+
+        let ollama_api = self.ollama_api.clone();
+        let events_tx = self.events_tx.clone();
+        let name = model.to_string();
+        let id = self.next_task_id();
+        let handle = tokio::spawn(async move {
+            let result = ollama_api.pull_model(&name, id, &events_tx).await;
+            let _ = events_tx.send(AppEvent::PullFinished(id, result));
+        });
+
+        self.pull = Some(PullTask {
+            id,
+            model: model.to_string(),
+            progress: None,
+            handle,
+        });
+        self.status_message = None;
+    }
+
+    fn confirm_delete_model(&mut self, model: &str) {
+        if model.is_empty() {
+            self.set_status_error("Usage: /delete <model>".to_string());
+            return;
+        }
+        let Some(index) = self.find_model(model) else {
+            self.set_status_error(format!("No model named {} is installed", model));
+            return;
+        };
+        let name = self.models_info.models.models[index].name.clone();
+        self.ask_confirm(
+            format!("Delete {} from the server's disk?", name),
+            ConfirmAction::DeleteModel(name),
+        );
+    }
+
+    fn delete_model(&mut self, model: String) {
+        let ollama_api = self.ollama_api.clone();
+        let events_tx = self.events_tx.clone();
+        tokio::spawn(async move {
+            let result = ollama_api.delete_model(&model).await;
+            let _ = events_tx.send(AppEvent::DeleteFinished(model, result));
+        });
+    }
+
+    // Ollama has no cancel endpoint; dropping the connection is what stops the pull
+    fn cancel_pull(&mut self) {
+        if let Some(pull) = self.pull.take() {
+            pull.handle.abort();
+            self.set_status(format!("Cancelled pull of {}", pull.model));
+        }
+    }
+
+    fn set_context_turns(&mut self, argument: &str) {
+        let turns = match argument {
+            "all" => None,
+            argument => match argument.parse::<usize>() {
+                Ok(turns) => Some(turns),
+                Err(_) => {
+                    self.set_status_error("Usage: /context <turns|all>".to_string());
+                    return;
+                }
+            },
+        };
+        self.config.context_turns = turns;
+        if !self.save_config() {
+            return;
+        }
+        self.set_status(format!("Sending {} as context", self.context_description()));
+    }
+
+    fn context_description(&self) -> String {
+        match self.config.context_turns {
+            None => "the whole conversation".to_string(),
+            Some(turns) => format!("the last {} turns", turns),
+        }
+    }
+
+    // Applies to the selected model and is remembered with its settings; no argument resets it
+    fn set_num_ctx(&mut self, argument: &str) {
+        if self.selected_model.name.is_empty() {
+            self.set_status_error("Select a model before setting num_ctx".to_string());
+            return;
+        }
+
+        let num_ctx = match argument {
+            "" => None,
+            argument => match argument.parse::<u32>() {
+                Ok(num_ctx) if num_ctx > 0 => Some(num_ctx),
+                _ => {
+                    self.set_status_error("Usage: /numctx <tokens>".to_string());
+                    return;
+                }
+            },
+        };
+
+        let mut options = self.model_settings.options.take().unwrap_or_default();
+        options.num_ctx = num_ctx;
+        self.model_settings.options = (options != ModelOptions::default()).then_some(options);
+        self.remember_model_settings();
+        self.set_status(format!("Context window: {} tokens", self.context_limit()));
+    }
+
+    // `/set temperature 0.2`; without a value the option goes back to the model default,
+    // without anything the current options are shown
+    fn set_model_option(&mut self, argument: &str) {
+        if self.selected_model.name.is_empty() {
+            self.set_status_error("Select a model before setting options".to_string());
+            return;
+        }
+
+        let mut options = self.model_settings.options.clone().unwrap_or_default();
+        if argument.is_empty() {
+            let pairs = options.display_pairs();
+            self.push_system_response(format!(
+                "Options for {}: {}",
+                self.selected_model.name,
+                if pairs.is_empty() {
+                    "model defaults"
+                } else {
+                    &pairs
+                }
+            ));
+            return;
+        }
+
+        let (key, value) = argument
+            .split_once(char::is_whitespace)
+            .map(|(key, value)| (key, value.trim()))
+            .unwrap_or((argument, ""));
+        if let Err(error) = options.set(key, value) {
+            self.push_system_response(format!("{} (Usage: /set <option> [value])", error));
+            return;
+        }
+        self.model_settings.options = (options != ModelOptions::default()).then_some(options);
+        self.remember_model_settings();
+        self.set_status(match value {
+            "" => format!("{} back to the model default", key),
+            value => format!("{} = {} for {}", key, value, self.selected_model.name),
+        });
+    }
+
+    fn import_conversation(&mut self, path: &str) {
+        if path.is_empty() {
+            self.set_status_error("Usage: /import <path>".to_string());
+            return;
+        }
+
+        match conversation::import(Path::new(path)) {
+            Ok(imported) => {
+                let count = imported.history.len();
+                // a saved scroll position only means something when the import is the whole chat
+                match imported.scroll {
+                    Some(scroll) if self.chat_log.history.is_empty() => {
+                        self.chat_scroll = scroll;
+                        self.follow_tail = false;
+                        // what was saved has been read already, it isn't new
+                        self.seen_line_count = None;
+                    }
+                    _ => self.scroll_to_bottom(),
+                }
+                // saved mid-reply, nothing is going to finish it now
+                self.chat_log
+                    .history
+                    .extend(imported.history.into_iter().map(|mut chat| {
+                        chat.end_stream();
+                        chat
+                    }));
+                self.set_status(format!("Imported {} messages from {}", count, path));
+            }
+            Err(error) => self.set_status_error(format!("Import failed: {}: {}", path, error)),
+        }
+    }
+
+    fn export_conversation(&mut self, path: &str) {
+        if path.is_empty() {
+            self.set_status_error("Usage: /export <path.md>".to_string());
+            return;
+        }
+        let model = if self.selected_model.name.is_empty() {
+            "no model"
+        } else {
+            &self.selected_model.name
+        };
+        match conversation::export_markdown(Path::new(path), &self.chat_log.history, model) {
+            Ok(()) => {
+                self.mark_saved();
+                self.set_status(format!("Exported to {}", path))
+            }
+            Err(error) => self.set_status_error(format!("Export failed: {}: {}", path, error)),
+        }
+    }
+
+    fn attach_image(&mut self, path: &str) {
+        if path.is_empty() {
+            self.pending_images.clear();
+            self.set_status("No images attached".to_string());
+            return;
+        }
+        if !self
+            .selected_model
+            .capabilities
+            .contains(&Capability::Vision)
+        {
+            self.set_status_error(format!(
+                "{} doesn't take images, pick a vision model first",
+                self.selected_model.name
+            ));
+            return;
+        }
+        match std::fs::read(path) {
+            Ok(image) => {
+                self.pending_images.push(BASE64_STANDARD.encode(image));
+                self.set_status(format!("Attached {}", path));
+            }
+            Err(error) => self.set_status_error(format!("Can't read {}: {}", path, error)),
+        }
+    }
+
+    fn save_conversation(&mut self, name: &str) {
+        if name.is_empty() {
+            self.set_status_error("Usage: /save <name>".to_string());
+            return;
+        }
+        if self.chat_log.history.is_empty() {
+            self.set_status_error("Nothing to save yet".to_string());
+            return;
+        }
+        self.chat_log.scroll = (!self.follow_tail).then_some(self.chat_scroll);
+        let saved = conversation::saved_path(name)
+            .and_then(|path| conversation::save(&path, &self.chat_log).map(|_| path));
+        match saved {
+            Ok(path) => {
+                self.mark_saved();
+                self.set_status(format!("Saved as {}", path.display()))
+            }
+            Err(error) => self.set_status_error(format!("Can't save {}: {}", name, error)),
+        }
+    }
+
+    // Like /clear followed by an /import of the saved file
+    fn load_conversation(&mut self, name: &str) {
+        if name.is_empty() {
+            self.set_status_error("Usage: /load <name>".to_string());
+            return;
+        }
+        let loaded = conversation::saved_path(name).and_then(|path| conversation::import(&path));
+        let chat_log = match loaded {
+            Ok(chat_log) => chat_log,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                self.set_status_error(format!("No conversation saved as {}", name));
+                return;
+            }
+            Err(error) => {
+                self.set_status_error(format!("Can't load {}: {}", name, error));
+                return;
+            }
+        };
+
+        self.clear_chat();
+        match chat_log.scroll {
+            Some(scroll) => {
+                self.chat_scroll = scroll;
+                self.follow_tail = false;
+            }
+            None => self.scroll_to_bottom(),
+        }
+        self.seen_line_count = None;
+        self.set_status(format!(
+            "Loaded {} ({} messages)",
+            name,
+            chat_log.history.len()
+        ));
+        self.chat_log = chat_log;
+        self.chat_log.history.iter_mut().for_each(Chat::end_stream);
+        self.mark_saved();
+    }
+
+    fn mark_saved(&mut self) {
+        self.saved_history = serde_json::to_string(&self.chat_log.history).ok();
+    }
+
+    // Nothing has changed since the conversation was last written out
+    fn is_saved(&self) -> bool {
+        self.saved_history.is_some()
+            && serde_json::to_string(&self.chat_log.history).ok() == self.saved_history
+    }
+
+    // Command output lives in the chat log but never goes to the model
+    fn push_system_response(&mut self, text: String) {
+        self.chat_log.history.push(Chat {
+            author: "system".to_string(),
+            content: text.clone(),
+            origin_content: vec![ChatType::SystemResponse(text)],
+            model_override: None,
+        });
+    }
+
+    // Rings the terminal bell for finished work, but only while the window is in the background
+    fn notify(&self) {
+        if self.focused {
+            return;
+        }
+        let mut stdout = std::io::stdout();
+        let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+    }
+
+    fn set_status(&mut self, text: String) {
+        self.status_message = Some(StatusMessage {
+            text,
+            is_error: false,
+        });
+    }
+
+    fn set_status_error(&mut self, text: String) {
+        self.status_message = Some(StatusMessage {
+            text,
+            is_error: true,
+        });
+    }
+
+    // One reply at a time, since Esc and incoming events only know the one generation
+    fn still_generating(&mut self) -> bool {
+        if self.generation.is_some() {
+            self.set_status_error("Still replying, Esc or Ctrl+C: stop".to_string());
+        }
+        self.generation.is_some()
+    }
+
+    // The prompt behind the latest reply, if the conversation ends in one
+    fn last_exchange_prompt(&self) -> Option<&Chat> {
+        match self.chat_log.history.as_slice() {
+            [.., prompt, reply] if prompt.author == "user" && reply.author == "assistant" => {
+                Some(prompt)
+            }
+            _ => None,
+        }
+    }
+
+    // Opens the input prefilled with the options the reply was made with, ready to tweak
+    fn start_regenerate(&mut self) {
+        if self.selected_model.name.is_empty() || self.still_generating() {
+            return;
+        }
+        let Some(prompt) = self.last_exchange_prompt() else {
+            self.set_status_error("Nothing to regenerate".to_string());
+            return;
+        };
+        let used_options = prompt
+            .origin_content
+            .iter()
+            .find_map(|origin| match origin {
+                ChatType::OllamaRequest(request) => request.options.clone(),
+                _ => None,
+            })
+            .or_else(|| self.request_options())
+            .unwrap_or_default();
+
+        let previous_input = std::mem::take(&mut self.input);
+        self.regenerate_input = Some(previous_input);
+        self.set_input(used_options.display_pairs());
+        self.input_mode = InputMode::Editing;
+    }
+
+    // The input becomes the editor; a prompt with several lines opens expanded
+    fn start_system_prompt_edit(&mut self) {
+        if self.selected_model.name.is_empty() {
+            self.set_status_error("Select a model to edit its system prompt".to_string());
+            return;
+        }
+        let system_prompt = self
+            .model_settings
+            .system_prompt
+            .clone()
+            .unwrap_or_default();
+        self.composing = system_prompt.contains('\n');
+        let previous_input = std::mem::take(&mut self.input);
+        self.system_prompt_input = Some(previous_input);
+        self.set_input(system_prompt);
+        self.input_mode = InputMode::Editing;
+    }
+
+    fn cancel_system_prompt(&mut self) {
+        if let Some(previous_input) = self.system_prompt_input.take() {
+            self.set_input(previous_input);
+        }
+    }
+
+    // Applies to the next request and is remembered with the model's other settings
+    fn submit_system_prompt(&mut self) {
+        let system_prompt = self.input.trim().to_string();
+        self.cancel_system_prompt();
+        self.model_settings.system_prompt = (!system_prompt.is_empty()).then_some(system_prompt);
+        self.remember_model_settings();
+        self.set_status(match &self.model_settings.system_prompt {
+            Some(_) => format!("System prompt set for {}", self.selected_model.name),
+            None => format!("System prompt cleared for {}", self.selected_model.name),
+        });
+    }
+
+    // Only for this session; the model's saved prompt comes back once this is cleared
+    fn set_session_system_prompt(&mut self, system_prompt: &str) {
+        self.system_prompt = (!system_prompt.is_empty()).then(|| system_prompt.to_string());
+        self.set_status(
+            match (&self.system_prompt, &self.model_settings.system_prompt) {
+                (Some(_), _) => "System prompt set for this session".to_string(),
+                (None, Some(_)) => "Session system prompt cleared, using the model's".to_string(),
+                (None, None) => "System prompt cleared".to_string(),
+            },
+        );
+    }
+
+    fn cancel_regenerate(&mut self) {
+        if let Some(previous_input) = self.regenerate_input.take() {
+            self.set_input(previous_input);
+        }
+    }
+
+    // Drops the last exchange and asks again with the typed options in place of the model's
+    fn submit_regenerate(&mut self) {
+        let options = match ModelOptions::parse_pairs(&self.input) {
+            Ok(options) => options,
+            Err(error) => {
+                self.set_status_error(format!("Regenerate: {}", error));
+                return;
+            }
+        };
+        // the prompt goes again as it was, images included
+        let Some((prompt, images, model_override)) = self.last_exchange_prompt().map(|chat| {
+            (
+                chat.content.clone(),
+                chat.images_request().unwrap_or_default(),
+                chat.model_override.clone(),
+            )
+        }) else {
+            self.cancel_regenerate();
+            return;
+        };
+
+        self.cancel_regenerate();
+        self.chat_log
+            .history
+            .truncate(self.chat_log.history.len() - 2);
+        self.set_status(match options.display_pairs().as_str() {
+            "" => "Regenerating with the model defaults".to_string(),
+            pairs => format!("Regenerating with {}", pairs),
+        });
+        let options = (options != ModelOptions::default()).then_some(options);
+        self.send_prompt_with_options(prompt, images, options, model_override);
+    }
+
+    // Asks the model to pick up where a reply cut off by the length limit stopped
+    fn continue_response(&mut self) {
+        if self.still_generating() {
+            return;
+        }
+        if !self.last_reply_truncated() || self.selected_model.name.is_empty() {
+            return;
+        }
+
+        let chat_request = self.chat_request(self.continuation_messages());
+        self.continue_chat(chat_request);
+    }
+
+    // The exchange being continued, with the partial reply, followed by the continue prompt
+    fn continuation_messages(&self) -> Vec<MessageChunk> {
+        let turns = self.config.context_turns.map(|turns| turns.max(1));
+        let mut messages: Vec<MessageChunk> = self.system_message().into_iter().collect();
+        messages.extend(self.history_messages(turns));
+        messages.push(MessageChunk {
+            role: "user".to_string(),
+            content: CONTINUE_PROMPT.to_string(),
+            images: None,
+            tool_calls: None,
+        });
+        messages
+    }
+
+    // A reply cut off by the length limit or by the stream closing can both be continued
+    fn last_reply_truncated(&self) -> bool {
+        self.chat_log
+            .history
+            .last()
+            .is_some_and(|chat| chat.is_truncated() || chat.ended_early())
+    }
+
+    fn send_prompt(&mut self, prompt: String, model_override: Option<String>) {
+        let images = std::mem::take(&mut self.pending_images);
+        self.send_prompt_with_options(prompt, images, self.request_options(), model_override);
+    }
+
+    // The options end up in the stored request, so the debug overlay shows what each reply used
+    fn send_prompt_with_options(
+        &mut self,
+        prompt: String,
+        images: Vec<String>,
+        options: Option<ModelOptions>,
+        model_override: Option<String>,
+    ) {
+        let mut chat_request = self.chat_request(self.context_messages(&prompt));
+        chat_request.options = options;
+        if let Some(model) = &model_override {
+            chat_request.model = model.clone();
+        }
+        if !images.is_empty() {
+            if let Some(prompt_message) = chat_request.messages.last_mut() {
+                prompt_message.images = Some(images);
+            }
+        }
+        let input_chat = Chat {
+            author: "user".to_string(),
+            content: prompt.clone(),
+            origin_content: vec![ChatType::OllamaRequest(chat_request.clone())],
+            model_override,
+        };
+        self.chat_log.history.push(input_chat);
+        self.chat(chat_request);
+    }
+
+    // The tool the latest reply asked to call, while its result has not been given yet
+    fn pending_tool_call(&self) -> Option<&str> {
+        let last_chat = self.chat_log.history.last()?;
+        if last_chat.author != "assistant" {
+            return None;
+        }
+        last_chat
+            .tool_calls()
+            .last()
+            .map(|tool_call| tool_call.function.name.as_str())
+    }
+
+    // The note lands after the reply, so the call is no longer the last thing and input is chat again
+    fn decline_tool_call(&mut self) {
+        match self.pending_tool_call() {
+            Some(name) => {
+                let note = format!(
+                    "Declined the call to {}, messages go to the model again",
+                    name
+                );
+                self.push_system_response(note);
+            }
+            None => self.set_status_error("No tool call to cancel".to_string()),
+        }
+    }
+
+    // The typed result goes back as a `tool` message after the reply that asked for it
+    fn send_tool_result(&mut self, result: String) {
+        let mut messages: Vec<MessageChunk> = self.system_message().into_iter().collect();
+        messages.extend(self.history_messages(self.config.context_turns.map(|turns| turns.max(1))));
+        messages.push(MessageChunk {
+            role: "tool".to_string(),
+            content: result.clone(),
+            images: None,
+            tool_calls: None,
+        });
+        let chat_request = self.chat_request(messages);
+
+        self.chat_log.history.push(Chat {
+            author: "tool".to_string(),
+            content: result,
+            origin_content: vec![ChatType::OllamaRequest(chat_request.clone())],
+            model_override: None,
+        });
+        self.chat(chat_request);
+    }
+
+    // Tokens the next request would take for the given prompt, counted from the texts alone so
+    // redrawing doesn't copy images and tool calls into a message list each frame
+    fn context_tokens(&self, prompt: &str) -> usize {
+        let history = if self.one_shot {
+            vec![]
+        } else {
+            self.history_chats(self.config.context_turns)
+        };
+        estimate_tokens(
+            self.active_system_prompt()
+                .into_iter()
+                .chain(history.iter().map(|chat| chat.content.as_str()))
+                .chain([prompt]),
+        )
+    }
+
+    // Messages that the next ChatRequest will carry for the given prompt
+    fn context_messages(&self, prompt: &str) -> Vec<MessageChunk> {
+        let mut messages: Vec<MessageChunk> = self.system_message().into_iter().collect();
+        if !self.one_shot {
+            messages.extend(self.history_messages(self.config.context_turns));
+        }
+        messages.push(MessageChunk {
+            role: "user".to_string(),
+            content: prompt.to_string(),
+            images: None,
+            tool_calls: None,
+        });
+        messages
+    }
+
+    fn history_messages(&self, turns: Option<usize>) -> Vec<MessageChunk> {
+        self.history_chats(turns)
+            .into_iter()
+            .map(|chat| MessageChunk {
+                role: chat.author.clone(),
+                content: chat.content.clone(),
+                // sent again with every request, the model keeps no memory of them
+                images: chat.images_request(),
+                tool_calls: chat.tool_calls_request(),
+            })
+            .collect()
+    }
+
+    // The last `turns` user/assistant exchanges, oldest first; None takes all of them.
+    // Command output and other system entries never go to the model
+    fn history_chats(&self, turns: Option<usize>) -> Vec<&Chat> {
+        let mut conversation: Vec<&Chat> = self
+            .chat_log
+            .history
+            .iter()
+            .filter(|chat| matches!(chat.author.as_str(), "user" | "assistant" | "tool"))
+            .collect();
+
+        let start = match turns {
+            None => 0,
+            Some(0) => conversation.len(),
+            Some(turns) => conversation
+                .iter()
+                .enumerate()
+                .filter(|(_, chat)| chat.author == "user")
+                .map(|(index, _)| index)
+                .nth_back(turns - 1)
+                .unwrap_or(0),
+        };
+
+        conversation.split_off(start)
+    }
+
+    fn system_message(&self) -> Option<MessageChunk> {
+        self.active_system_prompt()
+            .map(|system_prompt| MessageChunk {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+                images: None,
+                tool_calls: None,
+            })
+    }
+
+    fn active_system_prompt(&self) -> Option<&str> {
+        self.system_prompt
+            .as_deref()
+            .or(self.model_settings.system_prompt.as_deref())
+    }
+
+    // Per-model options, with num_ctx falling back to the global config value
+    fn request_options(&self) -> Option<ModelOptions> {
+        let mut options = self.model_settings.options.clone().unwrap_or_default();
+        if options.num_ctx.is_none() {
+            options.num_ctx = self.config.num_ctx;
+        }
+        (options != ModelOptions::default()).then_some(options)
+    }
+
+    fn context_limit(&self) -> usize {
+        self.request_options()
+            .and_then(|options| options.num_ctx)
+            .map_or(DEFAULT_NUM_CTX, |num_ctx| num_ctx as usize)
+    }
+
+    fn chat_request(&self, messages: Vec<MessageChunk>) -> ChatRequest {
+        ChatRequest {
+            model: self.selected_model.name.clone(),
+            messages,
+            options: self.request_options(),
+            keep_alive: self.model_settings.keep_alive.clone(),
+            tools: (!self.config.tools.is_empty()).then(|| self.config.tools.clone()),
+        }
+    }
+
+    fn conversation_turns(&self) -> usize {
+        self.chat_log
+            .history
+            .iter()
+            .filter(|chat| chat.author == "user")
+            .count()
+    }
+
+    fn get_character_index(&self) -> usize {
+        self.input
+            .char_indices()
+            .map(|(i, _)| i)
+            .nth(self.character_index)
+            .unwrap_or(self.input.len())
+    }
+
+    fn move_cursor_left(&mut self) {
+        let cursor_moved_left = self.character_index.saturating_sub(1);
+        self.character_index = self.clamp_cursor(cursor_moved_left);
+    }
+
+    fn move_cursor_right(&mut self) {
+        let cursor_moved_right = self.character_index.saturating_add(1);
+        self.character_index = self.clamp_cursor(cursor_moved_right);
+    }
+
+    fn clamp_cursor(&self, cursor_index: usize) -> usize {
+        cursor_index.clamp(0, self.input.chars().count())
+    }
+
+    fn reset_cursor(&mut self) {
+        self.character_index = 0;
+    }
+
+    fn update_input(&mut self, c: char) {
+        let index = self.get_character_index();
+        self.input.insert(index, c);
+        self.move_cursor_right();
+    }
+
+    fn delete_input(&mut self) {
+        let is_not_cursor_leftmost = self.character_index != 0;
+        if is_not_cursor_leftmost {
+            let current_index = self.character_index;
+            let from_left_to_current_index = current_index - 1;
+
+            let before_char_to_delete = self.input.chars().take(from_left_to_current_index);
+            let after_char_to_delete = self.input.chars().skip(current_index);
+
+            self.input = before_char_to_delete.chain(after_char_to_delete).collect();
+            self.move_cursor_left();
+        }
+    }
+}
+
+// UI
+impl App {
+    fn draw(&mut self, frame: &mut Frame) {
+        self.draw_widgets(frame);
+        if self.plain {
+            strip_colors(frame);
+        }
+    }
+
+    fn draw_widgets(&mut self, frame: &mut Frame) {
+        let [header_area, list_area, pull_area, status_area, footer_area] = Layout::vertical([
             Constraint::Length(6),
             Constraint::Fill(1),
+            Constraint::Length(self.pull.is_some().into()),
+            Constraint::Length(1),
             Constraint::Length(1),
         ])
         .areas(frame.area());
@@ -414,28 +3200,87 @@ impl App {
         ])
         .areas(list_area);
 
+        // the input spans the full width, so its rows can be laid out before the areas exist
+        let input_rows = input_rows(&self.input, frame.area().width.saturating_sub(2).into());
+        let input_height = if self.composing {
+            (chat_area.height * 4 / 5).max(MAX_INPUT_ROWS as u16 + 2)
+        } else {
+            input_rows.len().clamp(1, MAX_INPUT_ROWS) as u16 + 2
+        };
         let [chat_area, input_area] =
-            Layout::vertical([Constraint::Fill(1), Constraint::Length(3)]).areas(chat_area);
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(input_height)])
+                .areas(chat_area);
 
-        // TODO: length should be dynamic based on the number of models
-        let [list_area] = Layout::horizontal([Constraint::Length(15)])
-            .flex(Flex::Center)
-            .areas(list_area);
-        let [list_area] = Layout::vertical([Constraint::Length(2)])
+        // widest entry plus the highlight symbol
+        let list_width = self
+            .model_list_items()
+            .iter()
+            .map(ListItem::width)
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let [list_area, summary_area] =
+            Layout::vertical([Constraint::Length(2), Constraint::Length(1)])
+                .flex(Flex::Center)
+                .areas(list_area);
+        let [list_area] = Layout::horizontal([Constraint::Length(list_width as u16)])
             .flex(Flex::Center)
             .areas(list_area);
 
         self.render_header(frame, header_area);
         self.render_model_list(frame, list_area);
+        self.render_model_summary(frame, summary_area);
         self.render_chat(frame, chat_area);
-        self.render_text_input(frame, input_area);
+        let (cursor_row, cursor_column) = input_cursor(&input_rows, self.character_index);
+        // keeps the cursor's row in view once the box is full
+        let input_scroll =
+            (cursor_row + 1).saturating_sub(input_area.height.saturating_sub(2).into());
+        self.render_text_input(frame, input_area, &input_rows, input_scroll);
+        if let Some(pull) = &self.pull {
+            render_pull_progress(frame, pull_area, pull);
+        }
+        self.render_status(frame, status_area);
         self.render_helper(frame, footer_area);
 
+        if matches!(self.input_mode, InputMode::Editing) && self.template_fill.is_none() {
+            render_command_completions(frame, input_area, &command::completions(&self.input));
+        }
+
+        if self.debug_overlay {
+            self.render_debug_overlay(frame);
+            return;
+        }
+
+        if let Some(picker) = &mut self.template_picker {
+            render_template_picker(frame, &self.config.templates, picker);
+            return;
+        }
+
+        if let Some(confirm) = &self.confirm {
+            render_confirm(frame, confirm);
+            return;
+        }
+
+        if let Some(model) = self
+            .model_details
+            .and_then(|index| self.models_info.models.models.get(index))
+        {
+            render_model_details(frame, model);
+            return;
+        }
+
         match self.input_mode {
-            InputMode::Normal => {}
+            InputMode::Normal => {
+                if let Some(search) = self.search.as_ref().filter(|search| search.editing) {
+                    frame.set_cursor_position(Position::new(
+                        input_area.x + search.query.chars().count() as u16 + 1,
+                        input_area.y + 1,
+                    ));
+                }
+            }
             InputMode::Editing => frame.set_cursor_position(Position::new(
-                input_area.x + self.character_index as u16 + 1,
-                input_area.y + 1,
+                input_area.x + cursor_column as u16 + 1,
+                input_area.y + (cursor_row - input_scroll) as u16 + 1,
             )),
         }
 
@@ -443,30 +3288,63 @@ impl App {
     }
 
     fn render_header(&mut self, frame: &mut Frame, area: Rect) {
-        let logo = r"       _ _                             _         _                
-  ___ | | | __ _ _ __ ___   __ _      | |_ _   _(_)      _ __ ___ 
- / _ \| | |/ _` | '_ ` _ \ / _` |_____| __| | | | |_____| '__/ __|
-| (_) | | | (_| | | | | | | (_| |_____| |_| |_| | |_____| |  \__ \
- \___/|_|_|\__,_|_| |_| |_|\__,_|      \__|\__,_|_|     |_|  |___/";
+        let models = &self.models_info.models.models;
+        let total_size: u64 = models.iter().map(|model| model.size).sum();
+        let disk_usage = format!(
+            "{} models · {} · {}",
+            models.len(),
+            human_size(total_size),
+            capability::legend()
+        );
+
+        // centering clips a banner wider than the area on both sides, so use the widest that fits
+        let mut header = match LOGOS
+            .iter()
+            .find(|logo| logo_width(logo) <= area.width as usize)
+        {
+            Some(logo) => Text::raw(*logo),
+            None => Text::from(Line::from("ollama-tui-rs").bold()),
+        };
+        header.push_line(Line::from(disk_usage).dim());
 
-        let header = Paragraph::new(logo).centered();
-        frame.render_widget(header, area);
+        frame.render_widget(Paragraph::new(header).centered(), area);
     }
 
-    fn render_model_list(&mut self, frame: &mut Frame, area: Rect) {
-        let items: Vec<ListItem> = self
-            .models_info
-            .models
-            .models
+    fn model_list_items(&self) -> Vec<ListItem<'static>> {
+        let now = chrono::Utc::now();
+        let models = &self.models_info.models.models;
+        let number_width = models.len().to_string().len();
+        models
             .iter()
-            .map(|model| {
-                if model.name == self.selected_model.name {
-                    ListItem::new(format!("✓ {}", model.name).fg(Color::Green))
+            .enumerate()
+            .map(|(index, model)| {
+                let favorite = if self.config.is_favorite(&model.name) {
+                    "★ "
                 } else {
-                    ListItem::new(format!("☐ {}", model.name))
+                    ""
+                };
+                let mut line = if model.name == self.selected_model.name {
+                    Line::from(format!("✓ {}{}", favorite, model.name).fg(Color::Green))
+                } else {
+                    Line::from(format!("☐ {}{}", favorite, model.name))
+                };
+                line.spans.insert(
+                    0,
+                    format!("{:>width$}. ", index + 1, width = number_width).dim(),
+                );
+                if !model.capabilities.is_empty() {
+                    line.push_span(format!(" {}", capability::badges(&model.capabilities)).cyan());
+                }
+                if let Some(modified) = relative_time::relative_time(&model.modified_at, now) {
+                    line.push_span(format!(" · {}", modified).dim());
                 }
+                ListItem::new(line)
             })
-            .collect();
+            .collect()
+    }
+
+    fn render_model_list(&mut self, frame: &mut Frame, area: Rect) {
+        let items = self.model_list_items();
 
         let list = List::new(items)
             .highlight_symbol("> ")
@@ -475,37 +3353,223 @@ impl App {
         frame.render_stateful_widget(list, area, &mut self.models_info.selected_model);
     }
 
+    // Follows the list cursor, so sizes can be compared before choosing
+    fn render_model_summary(&self, frame: &mut Frame, area: Rect) {
+        let Some(model) = self
+            .models_info
+            .selected_model
+            .selected()
+            .and_then(|index| self.models_info.models.models.get(index))
+        else {
+            return;
+        };
+        frame.render_widget(
+            Paragraph::new(Line::from(model_summary(model)).dim()).centered(),
+            area,
+        );
+    }
+
     // TODO: Scrollbar
     fn render_chat(&mut self, frame: &mut Frame, area: Rect) {
-        let chat_log: Vec<Line> = self
-            .chat_log
-            .history
-            .iter()
-            .map(|history| {
-                Line::from(Span::raw(format!(
-                    "{}: {}",
-                    history.author, history.content
-                )))
-            })
-            .collect();
+        let inner_width = area.width.saturating_sub(2);
+        let continue_key = self.keymap.label(Action::Continue);
+        // the whole log is one Text, every message already split into its lines
+        let mut chat_log = Text::default();
+        // (wrapped line where the preview starts, image data) for each reserved preview
+        let mut image_slots: Vec<(usize, &str)> = vec![];
+        let mut line_offset = 0;
+        self.message_line_offsets.clear();
+        for (chat_index, history) in self.chat_log.history.iter().enumerate() {
+            // a run of messages from the same author is labelled once
+            let label = (chat_index == 0
+                || self.chat_log.history[chat_index - 1].author != history.author)
+                .then_some(history.author.as_str());
+            let mut lines = if history.is_empty_reply() {
+                let mut lines = message_lines(&history.author, label, "", &[]);
+                lines[0].push_span(
+                    Span::raw("(empty response — the model stopped immediately or its output was filtered)")
+                        .dim()
+                        .italic(),
+                );
+                lines
+            } else {
+                let matches = match &self.search {
+                    Some(search) => search.matches_in(chat_index),
+                    None => vec![],
+                };
+                message_lines(&history.author, label, &history.content, &matches)
+            };
+            if let Some(model) = &history.model_override {
+                lines[0]
+                    .spans
+                    .insert(1, Span::raw(format!("@{} ", model)).fg(Color::Magenta));
+            }
+            if history.is_system_response() {
+                lines = lines.into_iter().map(|line| line.dim().italic()).collect();
+            }
+            if history.is_waiting() {
+                lines[0].push_span(
+                    Span::raw(format!("{} thinking…", waiting_indicator(self.plain)))
+                        .fg(Color::Yellow)
+                        .dim(),
+                );
+            } else if history.is_streaming() {
+                // text still arriving stays faint until the final chunk settles it
+                lines = lines.into_iter().map(|line| line.dim()).collect();
+                if let Some(last_line) = lines.last_mut() {
+                    last_line
+                        .push_span(Span::raw(streaming_indicator(self.plain)).fg(Color::Yellow));
+                }
+            }
+            if history.is_stopped() {
+                lines.push(Line::from("(stopped)").dim().italic());
+            }
+            if history.ended_early() {
+                lines.push(
+                    Line::from(format!(
+                        "(the connection closed before the reply finished, {}: continue)",
+                        continue_key
+                    ))
+                    .dim()
+                    .italic(),
+                );
+            }
+            if let Some(stats) = history.generation_stats() {
+                lines.push(Line::from(format!("({})", stats)).dim());
+            }
+            if history.is_truncated() {
+                lines.push(
+                    Line::from(format!(
+                        "(response truncated — hit num_predict/context limit, {}: continue)",
+                        continue_key
+                    ))
+                    .dim()
+                    .italic(),
+                );
+            }
+
+            for tool_call in history.tool_calls() {
+                lines.push(
+                    Line::from(format!(
+                        "🔧 calling {}({})",
+                        tool_call.function.name, tool_call.function.arguments
+                    ))
+                    .fg(Color::Magenta)
+                    .bold(),
+                );
+            }
+
+            for image in history.images() {
+                lines.push(Line::from("[image]").dim());
+                if self.image_picker.is_some() {
+                    let preview_line =
+                        line_offset + rendered_line_count(&lines, inner_width, self.chat_wrap);
+                    image_slots.push((preview_line, image));
+                    lines.extend((0..IMAGE_PREVIEW_HEIGHT).map(|_| Line::default()));
+                }
+            }
+
+            self.message_line_offsets.push(line_offset);
+            line_offset += rendered_line_count(&lines, inner_width, self.chat_wrap);
+            chat_log.lines.extend(lines);
+        }
+
+        // without wrapping, long lines are reached by scrolling sideways instead
+        let widest_line = chat_log.width();
+        self.chat_scroll_x = self
+            .chat_scroll_x
+            .min(widest_line.saturating_sub(inner_width.into()));
+
+        let chat = chat_paragraph(chat_log, self.chat_wrap);
+        // measured before the block is attached, against the width inside the borders
+        self.chat_line_count = chat.line_count(inner_width);
+        let max_scroll = self
+            .chat_line_count
+            .saturating_sub(area.height.saturating_sub(2).into());
+        // a wider window or a toggled wrap can leave the old offset past the end
+        self.chat_scroll = if self.follow_tail {
+            max_scroll
+        } else {
+            self.chat_scroll.min(max_scroll)
+        };
+        let visible_lines: usize = area.height.saturating_sub(2).into();
+        if self.seen_line_count.is_none()
+            || self.chat_scroll + visible_lines >= self.chat_line_count
+        {
+            self.seen_line_count = Some(self.chat_line_count);
+        }
+        let unread = self
+            .seen_line_count
+            .is_some_and(|seen| self.chat_line_count > seen);
+
+        let mut block = Block::bordered().title(if self.chat_wrap {
+            "Chat"
+        } else {
+            "Chat (no wrap)"
+        });
+        if self.chat_line_count > 0 {
+            let first_visible_line = (self.chat_scroll + 1).min(self.chat_line_count);
+            let mut position = format!(" line {}/{} ", first_visible_line, self.chat_line_count);
+            if !self.chat_wrap {
+                position.push_str(&format!("col {} ", self.chat_scroll_x + 1));
+            }
+            block = block.title_bottom(Line::from(position).right_aligned());
+        }
+        if unread {
+            let badge = if self.plain { " v new " } else { " ▼ new " };
+            block = block.title_bottom(Line::from(badge).left_aligned().fg(Color::Yellow));
+        }
 
-        let chat = Paragraph::new(chat_log)
-            .wrap(Wrap { trim: true })
-            .block(Block::bordered().title("Chat"))
-            .scroll((self.chat_scroll as u16, 0));
+        let chat = chat
+            .block(block)
+            .scroll((self.chat_scroll as u16, self.chat_scroll_x as u16));
         frame.render_widget(chat, area);
 
-        self.chat_scroll_state = self.chat_scroll_state.content_length(
-            self.chat_log
-                .history
-                .len()
-                .saturating_sub(area.height as usize - 2),
-        );
+        // previews are drawn over their blank rows, only when fully in view
+        if let Some(picker) = &self.image_picker {
+            let inner = area.inner(Margin {
+                vertical: 1,
+                horizontal: 1,
+            });
+            for (preview_line, image) in image_slots {
+                let Some(top) = preview_line.checked_sub(self.chat_scroll) else {
+                    continue;
+                };
+                if top + IMAGE_PREVIEW_HEIGHT as usize > inner.height as usize {
+                    continue;
+                }
+
+                let preview = self
+                    .image_previews
+                    .entry(image.to_string())
+                    .or_insert_with(|| image_preview(picker, image));
+                if let Some(preview) = preview {
+                    let preview_area = Rect::new(
+                        inner.x,
+                        inner.y + top as u16,
+                        inner.width.min(IMAGE_PREVIEW_WIDTH),
+                        IMAGE_PREVIEW_HEIGHT,
+                    );
+                    frame.render_widget(Image::new(preview), preview_area);
+                }
+            }
+        }
+
+        // the thumb tracks wrapped lines, the same unit as chat_scroll
+        self.chat_scroll_state = self
+            .chat_scroll_state
+            .content_length(self.chat_line_count.saturating_sub(visible_lines))
+            .position(self.chat_scroll);
 
         frame.render_stateful_widget(
             Scrollbar::new(ScrollbarOrientation::VerticalRight)
                 .begin_symbol(Some("↑"))
-                .end_symbol(Some("↓")),
+                .end_symbol(Some("↓"))
+                .end_style(if unread {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                }),
             area.inner(Margin {
                 vertical: 1,
                 horizontal: 0,
@@ -514,30 +3578,273 @@ impl App {
         );
     }
 
-    fn render_text_input(&mut self, frame: &mut Frame, area: Rect) {
-        let input = Paragraph::new(self.input.as_str())
+    // The most recent request as sent, followed by the response chunks that answered it
+    fn render_debug_overlay(&mut self, frame: &mut Frame) {
+        let origins: Vec<&ChatType> = self
+            .chat_log
+            .history
+            .iter()
+            .flat_map(|chat| chat.origin_content.iter())
+            .collect();
+        let last_request = origins
+            .iter()
+            .rposition(|origin| matches!(origin, ChatType::OllamaRequest(_)));
+
+        let mut lines: Vec<Line> = vec![];
+        match last_request {
+            Some(request_index) => {
+                if let ChatType::OllamaRequest(request) = origins[request_index] {
+                    let (endpoint, body) = self.ollama_api.chat_body(request);
+                    lines.push(Line::from(format!("POST {}", endpoint)).bold());
+                    let body = serde_json::to_string_pretty(&body).unwrap_or_default();
+                    lines.extend(body.lines().map(|line| Line::from(line.to_string())));
+                }
+
+                lines.push(Line::default());
+                lines.push(Line::from("Response chunks").bold());
+                // re-serialized from the parsed ChatResponse, one chunk per line as streamed
+                lines.extend(origins[request_index + 1..].iter().filter_map(
+                    |origin| match origin {
+                        ChatType::OllamaResponse(response) => {
+                            serde_json::to_string(response).ok().map(Line::from)
+                        }
+                        _ => None,
+                    },
+                ));
+            }
+            None => lines.push(Line::from("No request sent yet").dim()),
+        }
+
+        let area = frame.area().inner(Margin {
+            vertical: 2,
+            horizontal: 4,
+        });
+        let overlay = Paragraph::new(lines).wrap(Wrap { trim: false });
+        let line_count = overlay.line_count(area.width.saturating_sub(2)) as u16;
+        self.debug_scroll = self
+            .debug_scroll
+            .min(line_count.saturating_sub(area.height.saturating_sub(2)));
+
+        let overlay = overlay.scroll((self.debug_scroll, 0)).block(
+            Block::bordered()
+                .title("Debug: last exchange")
+                .title_bottom(
+                    Line::from(format!(
+                        " ▲ ▼ PgUp PgDn: scroll, {}/Esc: close ",
+                        self.keymap.label(Action::Debug)
+                    ))
+                    .dim(),
+                ),
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(overlay, area);
+    }
+
+    fn render_text_input(
+        &mut self,
+        frame: &mut Frame,
+        area: Rect,
+        rows: &[Range<usize>],
+        scroll: usize,
+    ) {
+        if let (Some(search), InputMode::Normal) = (&self.search, &self.input_mode) {
+            render_search_prompt(frame, area, search, &self.keymap);
+            return;
+        }
+
+        let title = match &self.template_fill {
+            Some(fill) => format!(
+                "{} · {{{{{}}}}} ({}/{}), Esc: cancel",
+                fill.template.name,
+                fill.placeholders[fill.values.len()],
+                fill.values.len() + 1,
+                fill.placeholders.len()
+            ),
+            None if self.system_prompt_input.is_some() => {
+                if self.composing {
+                    format!(
+                        "System prompt for {}, Enter: new line, Ctrl+S: apply, Esc: collapse",
+                        self.selected_model.name
+                    )
+                } else {
+                    format!(
+                        "System prompt for {}, Enter: apply (empty clears it), Ctrl+E: expand, Esc: cancel",
+                        self.selected_model.name
+                    )
+                }
+            }
+            None if self.composing => {
+                "Compose, Enter: new line, Ctrl+S: send, Esc: collapse".to_string()
+            }
+            None if self.regenerate_input.is_some() => {
+                "Regenerate with options (key=value …), Enter: regenerate, Esc: cancel".to_string()
+            }
+            None => match self.pending_tool_call() {
+                Some(name) => format!(
+                    "Result for {} (sent as a tool message, /cancel: decline)",
+                    name
+                ),
+                None => "Input".to_string(),
+            },
+        };
+        let characters: Vec<char> = self.input.chars().collect();
+        let lines: Vec<Line> = rows
+            .iter()
+            .map(|row| Line::from(characters[row.clone()].iter().collect::<String>()))
+            .collect();
+        let input = Paragraph::new(lines)
+            .scroll((scroll as u16, 0))
             .style(match self.input_mode {
                 InputMode::Normal => Style::default(),
                 InputMode::Editing => Style::default().fg(Color::Yellow),
             })
-            .block(Block::bordered().title("Input"));
+            .block(match self.pending_images.len() {
+                0 => Block::bordered().title(title),
+                count => Block::bordered().title(title).title_bottom(
+                    Line::from(format!(
+                        " {} {} attached ",
+                        count,
+                        if count == 1 { "image" } else { "images" }
+                    ))
+                    .fg(Color::Cyan),
+                ),
+            });
 
         frame.render_widget(input, area);
     }
 
-    fn render_helper(&mut self, frame: &mut Frame, area: Rect) {
-        let normal_mode_text = "▲ ▼: model select, Enter: choose model, e: edit, Esc: quit";
-        let editing_mode_text = "▲ ▼: chat scroll, Enter: send message, Esc: back to model select";
+    fn render_status(&mut self, frame: &mut Frame, area: Rect) {
+        let context_tokens = self.context_tokens(&self.input);
+        let context_limit = self.context_limit();
+        let context_style = if context_tokens >= context_limit {
+            Style::default().fg(Color::Red)
+        } else if context_tokens >= context_limit * 3 / 4 {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
 
-        let helper: Paragraph;
-        match self.input_mode {
-            InputMode::Normal => {
-                helper = Paragraph::new(normal_mode_text).centered();
+        // without color the dot alone can't say connected or not
+        let connection = match (self.connected, self.plain) {
+            (Some(true), false) => Span::raw("●").fg(Color::Green),
+            (Some(false), false) => Span::raw("●").fg(Color::Red),
+            (None, false) => Span::raw("○"),
+            (Some(true), true) => Span::raw("online"),
+            (Some(false), true) => Span::raw("offline"),
+            (None, true) => Span::raw("-"),
+        };
+        let model = if self.selected_model.name.is_empty() {
+            Span::raw("no model").dim()
+        } else {
+            Span::raw(self.selected_model.name.as_str())
+        };
+        let last_chat = self.chat_log.history.last();
+        let generating = self.generation.is_some();
+        let tokens_per_second = last_chat.and_then(Chat::tokens_per_second);
+
+        // its first few words are enough to recognise which prompt is on
+        let system_prompt = self.active_system_prompt().map(|prompt| {
+            let first_line = prompt.lines().next().unwrap_or_default();
+            match first_line.char_indices().nth(24) {
+                Some((end, _)) => format!("{}…", &first_line[..end]),
+                None if prompt.contains('\n') => format!("{}…", first_line),
+                None => first_line.to_string(),
             }
-            InputMode::Editing => {
-                helper = Paragraph::new(editing_mode_text).centered();
+        });
+        let mut status_bar = StatusBar::new(self.plain)
+            .section(Line::from(vec![connection, Span::raw(" "), model]))
+            .section_if(generating, || {
+                Line::from(format!(
+                    "generating{} (Esc/Ctrl+C: stop)",
+                    streaming_indicator(self.plain)
+                ))
+                .fg(Color::Yellow)
+            });
+        if let Some(tokens_per_second) = tokens_per_second.filter(|_| !generating) {
+            status_bar = status_bar.section(format!("{:.1} tok/s", tokens_per_second));
+        }
+
+        // the progress itself is on the gauge above
+        if let Some(pull) = &self.pull {
+            status_bar = status_bar.section(
+                Line::from(format!("pulling {} (Esc: cancel)", pull.model)).fg(Color::Cyan),
+            );
+        } else if let Some(status_message) = &self.status_message {
+            let mut line = Line::from(status_message.text.as_str());
+            if status_message.is_error {
+                line = line.fg(Color::Red);
             }
+            status_bar = status_bar.section(line);
+        }
+
+        let status_bar = status_bar
+            .section_if(self.one_shot, || Line::from("one-shot").fg(Color::Magenta))
+            .section_if(self.dry_run, || Line::from("dry run").fg(Color::Magenta))
+            .section_if(system_prompt.is_some(), || {
+                Line::from(format!("system: {}", system_prompt.unwrap_or_default()))
+                    .fg(Color::Magenta)
+            })
+            .section(format!("turns: {}", self.conversation_turns()))
+            .section(
+                Line::from(format!(
+                    "context: ~{}/{} tokens",
+                    context_tokens, context_limit
+                ))
+                .style(context_style),
+            );
+        frame.render_widget(status_bar, area);
+    }
+
+    fn render_helper(&mut self, frame: &mut Frame, area: Rect) {
+        let key = |action| self.keymap.label(action);
+        let mut normal_mode_hints = vec![
+            format!(
+                "{} {} 0-9: model select",
+                key(Action::ModelPrevious),
+                key(Action::ModelNext)
+            ),
+            format!("{}: choose model", key(Action::SelectModel)),
+            format!("{}: details", key(Action::ModelDetails)),
+            format!("{}: favorite", key(Action::Favorite)),
+            format!("{}: edit", key(Action::Edit)),
+            format!("{}: system prompt", key(Action::SystemPrompt)),
+        ];
+        if self.last_reply_truncated() {
+            normal_mode_hints.push(format!("{}: continue", key(Action::Continue)));
+        }
+        if self.last_exchange_prompt().is_some() {
+            normal_mode_hints.push(format!("{}: regenerate", key(Action::Regenerate)));
+        }
+        if self.last_reply().is_some() {
+            normal_mode_hints.push(format!("{}: copy reply", key(Action::CopyReply)));
+        }
+        normal_mode_hints.extend([
+            format!("{}: templates", key(Action::Templates)),
+            format!("{}: search", key(Action::Search)),
+            format!("{}: debug", key(Action::Debug)),
+            format!("{}: latest", key(Action::ScrollBottom)),
+        ]);
+        if self.chat_wrap {
+            normal_mode_hints.push(format!("{}: no wrap", key(Action::ToggleWrap)));
+        } else {
+            normal_mode_hints.extend([
+                format!("{}: wrap", key(Action::ToggleWrap)),
+                format!(
+                    "{} {}: scroll",
+                    key(Action::ScrollLeft),
+                    key(Action::ScrollRight)
+                ),
+            ]);
         }
+        normal_mode_hints.push(format!("{}: quit", key(Action::Quit)));
+        let editing_mode_text =
+            "▲ ▼: chat scroll, End: latest, Enter: send message, Alt+Enter: new line, Ctrl+E: compose, Esc: back to model select";
+
+        let helper = match self.input_mode {
+            InputMode::Normal => Paragraph::new(normal_mode_hints.join(", ")).centered(),
+            InputMode::Editing => Paragraph::new(editing_mode_text).centered(),
+        };
 
         frame.render_widget(helper, area);
     }
@@ -548,38 +3855,247 @@ impl App {
     async fn load_models(&mut self) {
         let models = self.ollama_api.get_models().await;
         match models {
-            Ok(models) => {
+            Ok(mut models) => {
+                let shows = futures::future::join_all(
+                    models
+                        .models
+                        .iter()
+                        .map(|model| self.ollama_api.show_model(&model.name)),
+                )
+                .await;
+                for (model, show) in models.models.iter_mut().zip(shows) {
+                    model.capabilities = match show {
+                        Ok(show) if !show.capabilities.is_empty() => {
+                            capability::from_show(&show.capabilities)
+                        }
+                        _ => capability::guess(model),
+                    };
+                }
                 self.models_info.models = models;
+                self.sort_models();
+                self.connected = Some(true);
             }
             Err(error) => {
-                eprintln!("Error loading models: {}", error);
-                // TODO: show error message on tui
+                self.connected = Some(false);
+                self.set_status_error(format!(
+                    "Can't list models from {}: {}",
+                    self.ollama_api.base_url,
+                    send_error(error)
+                ));
             }
         }
     }
 
-    async fn chat(&mut self, chat_request: ChatRequest) {
-        let chat_response = self.ollama_api.chat(chat_request).await;
-        match chat_response {
-            Ok(chat_response) => {
-                for response in chat_response {
-                    let chat_response = Chat {
-                        author: "assistant".to_string(),
-                        content: response.message.content.clone(),
-                        origin_content: vec![ChatType::OllamaResponse(response.clone())],
-                    };
-                    self.update_chat_log(chat_response, true);
-                }
+    // The reply gets its own Chat right away, so the streaming indicator shows before the first chunk
+    fn chat(&mut self, chat_request: ChatRequest) {
+        // after sending, the new exchange is what to read, wherever the chat was scrolled
+        self.scroll_to_bottom();
+        if self.dry_run {
+            self.show_dry_run(&chat_request);
+            return;
+        }
+        self.chat_log.history.push(Chat {
+            author: "assistant".to_string(),
+            content: String::new(),
+            origin_content: vec![ChatType::OllamaRequest(chat_request.clone())],
+            model_override: None,
+        });
+        self.start_generation(chat_request, self.chat_log.history.len() - 1);
+    }
+
+    // The continuation is merged into the truncated assistant Chat instead of starting a new one
+    fn continue_chat(&mut self, chat_request: ChatRequest) {
+        if self.dry_run {
+            self.show_dry_run(&chat_request);
+            return;
+        }
+        let Some(last_chat) = self.chat_log.history.last_mut() else {
+            return;
+        };
+        last_chat
+            .origin_content
+            .push(ChatType::OllamaRequest(chat_request.clone()));
+        self.start_generation(chat_request, self.chat_log.history.len() - 1);
+    }
+
+    fn show_dry_run(&mut self, chat_request: &ChatRequest) {
+        let (endpoint, body) = self.ollama_api.chat_body(chat_request);
+        match serde_json::to_string_pretty(&body) {
+            Ok(json) => self
+                .push_system_response(format!("Dry run, not sent:\nPOST {}\n{}", endpoint, json)),
+            Err(error) => {
+                self.set_status_error(format!("Could not serialize the request: {}", error))
             }
+        }
+    }
+
+    fn start_generation(&mut self, chat_request: ChatRequest, chat_index: usize) {
+        let ollama_api = self.ollama_api.clone();
+        self.record_transcript(|transcript| {
+            let (endpoint, body) = ollama_api.chat_body(&chat_request);
+            transcript.request(endpoint, &body)
+        });
+        let events_tx = self.events_tx.clone();
+        let id = self.next_task_id();
+        let handle = tokio::spawn(async move {
+            let result = ollama_api.chat(&chat_request, id, &events_tx).await;
+            let _ = events_tx.send(AppEvent::ChatFinished(id, result));
+        });
+        self.generation = Some(Generation {
+            id,
+            handle,
+            chat_index,
+        });
+    }
+
+    fn next_task_id(&mut self) -> u64 {
+        self.last_task_id += 1;
+        self.last_task_id
+    }
+
+    fn is_current_generation(&self, id: u64) -> bool {
+        self.generation
+            .as_ref()
+            .is_some_and(|generation| generation.id == id)
+    }
+
+    // Every chunk goes into the one Chat that chat() pushed for the reply, raw response included
+    fn append_chunk(&mut self, chunk: ChatResponse) {
+        // chunks already queued when the reply was stopped are dropped
+        let Some(generation) = &self.generation else {
+            return;
+        };
+        let chat_index = generation.chat_index;
+        self.record_transcript(|transcript| transcript.response(&chunk));
+        if let Some(reply) = self.chat_log.history.get_mut(chat_index) {
+            reply.content.push_str(&chunk.message.content);
+            reply.origin_content.push(ChatType::OllamaResponse(chunk));
+        }
+    }
+
+    async fn finish_generation(&mut self, result: Result<(), std::io::Error>) {
+        let Some(generation) = self.generation.take() else {
+            return;
+        };
+        self.connected = Some(result.is_ok());
+        match result {
+            Ok(()) => self.finish_reply(generation.chat_index),
             Err(error) => {
-                eprintln!("Error chatting: {}", error);
-                // TODO: show error message on tui
+                if !self.discard_unanswered(generation.chat_index) {
+                    self.finish_reply(generation.chat_index);
+                }
+                if error.kind() == std::io::ErrorKind::NotFound {
+                    self.check_selected_model(error).await;
+                } else {
+                    self.set_status_error(format!("Error chatting: {}", error));
+                }
+            }
+        }
+    }
+
+    // The partial reply is kept as a finished message, so it is part of the context from here on
+    fn stop_generation(&mut self) {
+        let Some(generation) = self.generation.take() else {
+            return;
+        };
+        generation.handle.abort();
+        if !self.discard_unanswered(generation.chat_index) {
+            if let Some(reply) = self.chat_log.history.get_mut(generation.chat_index) {
+                reply
+                    .origin_content
+                    .push(ChatType::SystemResponse(REPLY_STOPPED.to_string()));
             }
         }
+        self.set_status("Stopped".to_string());
+    }
+
+    // Undoes the request of a reply that never got a chunk, returning whether there was one
+    fn discard_unanswered(&mut self, chat_index: usize) -> bool {
+        let Some(reply) = self.chat_log.history.get_mut(chat_index) else {
+            return false;
+        };
+        if !matches!(
+            reply.origin_content.last(),
+            Some(ChatType::OllamaRequest(_))
+        ) {
+            return false;
+        }
+
+        reply.origin_content.pop();
+        if reply.origin_content.is_empty() {
+            self.chat_log.history.remove(chat_index);
+        }
+        true
+    }
+
+    // A failed write turns logging off for the session instead of erroring on every chunk
+    fn record_transcript(&mut self, record: impl FnOnce(&mut Transcript) -> std::io::Result<()>) {
+        let Some(transcript) = &mut self.transcript else {
+            return;
+        };
+        if let Err(error) = record(transcript) {
+            self.transcript = None;
+            self.set_status_error(format!("Transcript logging stopped: {}", error));
+        }
+    }
+
+    // A 404 usually means the model went away on the server, so re-list before blaming anything else
+    async fn check_selected_model(&mut self, error: std::io::Error) {
+        self.load_models().await;
+        let still_there = self
+            .models_info
+            .models
+            .models
+            .iter()
+            .any(|model| model.name == self.selected_model.name);
+        if still_there {
+            self.set_status_error(format!("Error chatting: {}", error));
+            return;
+        }
+
+        self.set_status_error(format!(
+            "{} is no longer on the server, pick another model",
+            self.selected_model.name
+        ));
+        self.selected_model = Model::default();
+        self.model_settings = ModelSettings::default();
+        self.input_mode = InputMode::Normal;
+    }
+
+    // A reply whose last chunk is not done will never finish, so stop showing it as streaming
+    fn finish_reply(&mut self, chat_index: usize) {
+        if let Some(reply) = self.chat_log.history.get_mut(chat_index) {
+            reply.end_stream();
+        }
+        self.notify();
     }
 }
 
+// In raw mode Ctrl+C is a key press, so these only come from outside, e.g. `kill` or a process manager
+fn listen_for_signals(events: UnboundedSender<AppEvent>) {
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut terminate) => {
+                    terminate.recv().await;
+                }
+                Err(_) => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate => {}
+        }
+        let _ = events.send(AppEvent::Terminate);
+    });
+}
+
 fn shutdown() -> std::io::Result<()> {
+    crossterm::execute!(std::io::stdout(), crossterm::event::DisableFocusChange)?;
     crossterm::execute!(std::io::stderr(), crossterm::terminal::LeaveAlternateScreen)?;
     crossterm::terminal::disable_raw_mode()?;
     Ok(())
@@ -587,10 +4103,481 @@ fn shutdown() -> std::io::Result<()> {
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    // checked now, an error inside the alternate screen would vanish with it
+    let host = args.host.clone().or_else(|| {
+        std::env::var("OLLAMA_HOST")
+            .ok()
+            .filter(|host| !host.is_empty())
+    });
+    if let Some(host) = &host {
+        if let Err(error) = parse_host(host) {
+            eprintln!("Can't use host {}: {}", host, error);
+            std::process::exit(2);
+        }
+    }
+    if let (Some(model), Some(prompt)) = (&args.model, &args.prompt) {
+        return print_reply(model, prompt, host, args.system, args.dry_run).await;
+    }
+
     let terminal = ratatui::init();
+    crossterm::execute!(std::io::stdout(), crossterm::event::EnableFocusChange)?;
 
-    App::default().run(terminal).await?;
+    // halfblocks would work anywhere but is too coarse for a preview, so it falls back to "[image]".
+    // tmux swallows the query without passthrough, and the reader thread then eats our key presses
+    let image_picker = std::env::var_os("TMUX")
+        .is_none()
+        .then(Picker::from_query_stdio)
+        .and_then(Result::ok)
+        .filter(|picker| picker.protocol_type() != ProtocolType::Halfblocks);
+
+    // the terminal is restored even when the loop fails
+    let result = App {
+        image_picker,
+        debug_overlay: args.debug,
+        one_shot: args.one_shot,
+        dry_run: args.dry_run,
+        wanted_model: args.model,
+        pull_wanted_model: args.pull,
+        host,
+        system_prompt: args.system,
+        plain: plain_terminal(),
+        ..App::default()
+    }
+    .run(terminal)
+    .await;
     shutdown()?;
 
+    result
+}
+
+// The headless --prompt mode: same config and request as the TUI, the reply streams to stdout
+async fn print_reply(
+    model: &str,
+    prompt: &str,
+    host: Option<String>,
+    system_prompt: Option<String>,
+    dry_run: bool,
+) -> std::io::Result<()> {
+    let mut app = App {
+        host,
+        system_prompt,
+        ..App::default()
+    };
+    app.load_config();
+    if let Some(StatusMessage {
+        text,
+        is_error: true,
+    }) = &app.status_message
+    {
+        eprintln!("{}", text);
+    }
+    app.selected_model.name = model.to_string();
+    app.model_settings = app.config.model_settings(model);
+    let chat_request = app.chat_request(app.context_messages(prompt));
+    if dry_run {
+        // the same as the TUI's dry run shows; through writeln so a closed pipe (`| head`) is an
+        // error rather than a panic
+        let (endpoint, body) = app.ollama_api.chat_body(&chat_request);
+        writeln!(
+            std::io::stdout(),
+            "POST {}\n{}",
+            endpoint,
+            serde_json::to_string_pretty(&body)?
+        )?;
+        return Ok(());
+    }
+
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+    let printer = tokio::spawn(async move {
+        let mut stdout = std::io::stdout();
+        let mut printed = false;
+        while let Some(event) = events_rx.recv().await {
+            if let AppEvent::ChatChunk(_, chunk) = event {
+                stdout.write_all(chunk.message.content.as_bytes())?;
+                stdout.flush()?;
+                printed = true;
+            }
+        }
+        if printed {
+            writeln!(stdout)?;
+        }
+        Ok::<(), std::io::Error>(())
+    });
+    let result = app.ollama_api.chat(&chat_request, 0, &events_tx).await;
+    drop(events_tx);
+    printer.await.map_err(std::io::Error::other)??;
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const MODELS: &str = r#"{"models":[{"name":"llama3.2:latest","model":"llama3.2:latest",
+        "modified_at":"2026-10-01T10:00:00Z","size":2019393189,"digest":"a80c4f17acd5",
+        "details":{"format":"gguf","family":"llama","families":["llama"],
+        "parameter_size":"3.2B","quantization_level":"Q4_K_M"}}]}"#;
+
+    fn chunk(content: &str, done: bool) -> String {
+        format!(
+            r#"{{"model":"llama3.2","created_at":"2026-10-01T10:00:00Z","message":{{"role":"assistant","content":"{}"}},"done":{}}}"#,
+            content, done
+        )
+    }
+
+    fn chat_request() -> ChatRequest {
+        ChatRequest {
+            model: "llama3.2:latest".to_string(),
+            messages: vec![MessageChunk {
+                role: "user".to_string(),
+                content: "hi".to_string(),
+                images: None,
+                tool_calls: None,
+            }],
+            options: None,
+            keep_alive: None,
+            tools: None,
+        }
+    }
+
+    // The server shuts down when dropped, so tests hold on to it
+    async fn mock(route: &str, response: ResponseTemplate) -> (MockServer, OllamaApi) {
+        let server = MockServer::start().await;
+        Mock::given(path(route))
+            .respond_with(response)
+            .mount(&server)
+            .await;
+        let api = OllamaApi::new(&server.uri(), None).unwrap();
+        (server, api)
+    }
+
+    // Runs the request and collects the streamed replies
+    async fn chat(api: &OllamaApi) -> (Result<(), std::io::Error>, String) {
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        let result = api.chat(&chat_request(), 0, &events_tx).await;
+        drop(events_tx);
+        let mut content = String::new();
+        while let Some(event) = events_rx.recv().await {
+            if let AppEvent::ChatChunk(_, chunk) = event {
+                content.push_str(&chunk.message.content);
+            }
+        }
+        (result, content)
+    }
+
+    #[test]
+    fn plain_mode_keeps_search_highlights() {
+        let mut app = App {
+            plain: true,
+            ..App::default()
+        };
+        app.push_system_response("needle and needle".to_string());
+        let mut search = ChatSearch::new(0, true);
+        search.query = "needle".to_string();
+        search.update(&app.chat_log.history);
+        app.search = Some(search);
+
+        let mut terminal =
+            ratatui::Terminal::new(ratatui::backend::TestBackend::new(80, 24)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let marked = |modifier: Modifier| {
+            buffer
+                .content
+                .iter()
+                .filter(|cell| cell.modifier.contains(modifier) && cell.bg == Color::Reset)
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+        };
+        assert_eq!(marked(Modifier::REVERSED), "needle");
+        assert_eq!(marked(Modifier::UNDERLINED), "needle");
+    }
+
+    #[test]
+    fn openai_requests_link_tool_results_to_their_calls() {
+        let message = |role: &str, content: &str| MessageChunk {
+            role: role.to_string(),
+            content: content.to_string(),
+            images: None,
+            tool_calls: None,
+        };
+        let mut chat_request = chat_request();
+        chat_request.messages.push(MessageChunk {
+            tool_calls: Some(vec![ToolCall {
+                function: ToolCallFunction {
+                    name: "get_weather".to_string(),
+                    arguments: serde_json::json!({"city": "Seoul"}),
+                },
+            }]),
+            ..message("assistant", "")
+        });
+        chat_request.messages.push(message("tool", "sunny"));
+
+        let body = serde_json::to_value(openai::CompletionRequest::from(&chat_request)).unwrap();
+        let call = &body["messages"][1]["tool_calls"][0];
+        assert_eq!(call["type"], "function");
+        assert_eq!(call["function"]["arguments"], r#"{"city":"Seoul"}"#);
+        assert_eq!(body["messages"][2]["tool_call_id"], call["id"]);
+        assert!(body["messages"][0].get("tool_call_id").is_none());
+    }
+
+    #[test]
+    fn parse_host_fills_in_scheme_and_port() {
+        assert_eq!(parse_host("127.0.0.1").unwrap(), "http://127.0.0.1:11434");
+        assert_eq!(parse_host("0.0.0.0:8080").unwrap(), "http://0.0.0.0:8080");
+        assert_eq!(
+            parse_host("https://example.com/ollama/").unwrap(),
+            "https://example.com/ollama"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_models_lists_models() {
+        let (_server, api) = mock(
+            "/api/tags",
+            ResponseTemplate::new(200).set_body_string(MODELS),
+        )
+        .await;
+        let models = api.get_models().await.unwrap();
+        assert_eq!(models.models.len(), 1);
+        assert_eq!(models.models[0].name, "llama3.2:latest");
+    }
+
+    #[tokio::test]
+    async fn get_models_rejects_malformed_json() {
+        let (_server, api) = mock(
+            "/api/tags",
+            ResponseTemplate::new(200).set_body_string("{\"models\":"),
+        )
+        .await;
+        assert!(api.get_models().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_models_fails_on_error_status() {
+        let (_server, api) = mock(
+            "/api/tags",
+            ResponseTemplate::new(500).set_body_string(MODELS),
+        )
+        .await;
+        assert!(api.get_models().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn get_models_gives_up_after_retrying_a_timeout() {
+        let (server, api) = mock(
+            "/api/tags",
+            ResponseTemplate::new(200)
+                .set_body_string(MODELS)
+                .set_delay(Duration::from_millis(500)),
+        )
+        .await;
+        let api = OllamaApi {
+            timeout: Duration::from_millis(50),
+            ..api
+        };
+        assert!(api.get_models().await.unwrap_err().is_timeout());
+        let attempts = server.received_requests().await.unwrap().len();
+        assert_eq!(attempts, MODEL_LIST_RETRIES.len() + 1);
+    }
+
+    #[tokio::test]
+    async fn delete_model_reports_a_missing_model() {
+        let (_server, api) = mock(
+            "/api/delete",
+            ResponseTemplate::new(404).set_body_string(r#"{"error":"model 'nope' not found"}"#),
+        )
+        .await;
+        let error = api.delete_model("nope").await.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("model 'nope' not found"));
+    }
+
+    #[tokio::test]
+    async fn chat_streams_every_line() {
+        let body = format!(
+            "{}\n{}\n{}\n",
+            chunk("Hel", false),
+            chunk("lo", false),
+            chunk("", true)
+        );
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/api/chat"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+        let api = OllamaApi::new(&server.uri(), None).unwrap();
+
+        let (result, content) = chat(&api).await;
+        result.unwrap();
+        assert_eq!(content, "Hello");
+    }
+
+    #[tokio::test]
+    async fn chat_takes_a_last_line_without_newline() {
+        let body = format!("{}\n{}", chunk("Hi", false), chunk("", true));
+        let (_server, api) = mock(
+            "/api/chat",
+            ResponseTemplate::new(200).set_body_string(body),
+        )
+        .await;
+        let (result, content) = chat(&api).await;
+        result.unwrap();
+        assert_eq!(content, "Hi");
+    }
+
+    #[tokio::test]
+    async fn chat_rejects_malformed_json() {
+        let body = format!("{}\nnot json\n", chunk("Hi", false));
+        let (_server, api) = mock(
+            "/api/chat",
+            ResponseTemplate::new(200).set_body_string(body),
+        )
+        .await;
+        let (result, content) = chat(&api).await;
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(content, "Hi");
+    }
+
+    #[tokio::test]
+    async fn chat_reports_the_server_error() {
+        let body = r#"{"error":"model \"llama3.2:latest\" not found, try pulling it first"}"#;
+        let (_server, api) = mock(
+            "/api/chat",
+            ResponseTemplate::new(404).set_body_string(body),
+        )
+        .await;
+        let (result, _) = chat(&api).await;
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("try pulling it first"));
+    }
+
+    #[tokio::test]
+    async fn chat_reads_sse_events() {
+        let delta = |content: &str, finish: &str| {
+            format!(
+                r#"data: {{"model":"llama3.2","created":1790000000,"choices":[{{"index":0,"delta":{{"content":"{}"}},"finish_reason":{}}}]}}"#,
+                content, finish
+            )
+        };
+        let body = format!(
+            ": keep-alive\n\n{}\n\n{}\n\n{}\n\ndata: [DONE]\n\n",
+            delta("Hel", "null"),
+            delta("lo", "null"),
+            delta("", "\"stop\"")
+        );
+        let (_server, api) = mock(
+            "/api/chat",
+            ResponseTemplate::new(200).set_body_string(body),
+        )
+        .await;
+        let api = OllamaApi {
+            stream_format: StreamFormat::Sse,
+            ..api
+        };
+        let (result, content) = chat(&api).await;
+        result.unwrap();
+        assert_eq!(content, "Hello");
+    }
+
+    #[tokio::test]
+    async fn openai_flavor_lists_models_and_streams_completions() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"{"object":"list","data":[{"id":"gpt-4o-mini","object":"model","created":1790000000}]}"#,
+            ))
+            .mount(&server)
+            .await;
+        let body = concat!(
+            r#"data: {"model":"gpt-4o-mini","created":1790000000,"choices":[{"delta":{"role":"assistant","content":"Hi"},"finish_reason":null}]}"#,
+            "\n\n",
+            r#"data: {"model":"gpt-4o-mini","created":1790000000,"choices":[{"delta":{},"finish_reason":"stop"}]}"#,
+            "\n\n",
+            // the usage summary comes after the finish, with no choices
+            r#"data: {"model":"gpt-4o-mini","created":1790000000,"choices":[],"usage":{"completion_tokens":1}}"#,
+            "\n\ndata: [DONE]\n\n"
+        );
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(&server)
+            .await;
+        let api = OllamaApi {
+            flavor: ApiFlavor::OpenAI,
+            ..OllamaApi::new(&server.uri(), None).unwrap()
+        };
+
+        let models = api.get_models().await.unwrap();
+        assert_eq!(models.models[0].name, "gpt-4o-mini");
+        let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+        api.chat(&chat_request(), 0, &events_tx).await.unwrap();
+        drop(events_tx);
+        let mut chunks = vec![];
+        while let Some(event) = events_rx.recv().await {
+            if let AppEvent::ChatChunk(_, chunk) = event {
+                chunks.push(chunk);
+            }
+        }
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].message.content, "Hi");
+        assert!(chunks[1].done);
+    }
+
+    #[tokio::test]
+    async fn chat_joins_lines_split_across_chunks() {
+        // wiremock sends a body in one piece, so write the chunked response by hand
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let first = chunk("Hel", false);
+        let body = format!("{}\n{}\n{}\n", first, chunk("lo", false), chunk("", true));
+        // cut through the middle of the first object and again inside the second
+        let (start, rest) = body.split_at(first.len() / 2);
+        let (middle, end) = rest.split_at(first.len());
+        let pieces = [start.to_string(), middle.to_string(), end.to_string()];
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 4096];
+            let _ = tokio::io::AsyncReadExt::read(&mut stream, &mut request).await;
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\n\
+                        Transfer-Encoding: chunked\r\nConnection: close\r\n\r\n";
+            stream.write_all(head.as_bytes()).await.unwrap();
+            for piece in pieces {
+                let frame = format!("{:x}\r\n{}\r\n", piece.len(), piece);
+                stream.write_all(frame.as_bytes()).await.unwrap();
+                stream.flush().await.unwrap();
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+            stream.write_all(b"0\r\n\r\n").await.unwrap();
+        });
+
+        let api = OllamaApi::new(&format!("http://{}", address), None).unwrap();
+        let (result, content) = chat(&api).await;
+        result.unwrap();
+        assert_eq!(content, "Hello");
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatRequest, ChatResponse, MessageChunk, Model, ModelList, Tool, ToolCall};
+
+// One `data:` event of an OpenAI-style stream, as LiteLLM and other compatible servers send it
+#[derive(Deserialize, Debug)]
+pub struct CompletionChunk {
+    #[serde(default)]
+    model: String,
+    // unix seconds
+    #[serde(default)]
+    created: i64,
+    #[serde(default)]
+    choices: Vec<CompletionChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CompletionChoice {
+    #[serde(default)]
+    delta: CompletionDelta,
+    // "stop" or "length", the same reasons Ollama gives in done_reason
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct CompletionDelta {
+    role: Option<String>,
+    content: Option<String>,
+}
+
+impl CompletionChunk {
+    // Only the first choice is used, nothing here asks for more than one. A chunk without
+    // any, like the usage summary some servers send after the finish, has nothing to add
+    pub fn into_chat_response(self) -> Option<ChatResponse> {
+        let choice = self.choices.into_iter().next()?;
+        let finish_reason = choice.finish_reason;
+        let delta = choice.delta;
+        Some(ChatResponse {
+            model: self.model,
+            created_at: chrono::DateTime::from_timestamp(self.created, 0)
+                .map(|created| created.to_rfc3339())
+                .unwrap_or_default(),
+            message: MessageChunk {
+                role: delta.role.unwrap_or_else(|| "assistant".to_string()),
+                content: delta.content.unwrap_or_default(),
+                images: None,
+                tool_calls: None,
+            },
+            done: finish_reason.is_some(),
+            done_reason: finish_reason,
+            eval_count: None,
+            eval_duration: None,
+            total_duration: None,
+        })
+    }
+}
+
+// The body of POST /v1/chat/completions, made from the same ChatRequest as /api/chat.
+// Images don't carry over, those messages go as plain text
+#[derive(Serialize, Debug)]
+pub struct CompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<CompletionMessage<'a>>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<&'a [Tool]>,
+}
+
+#[derive(Serialize, Debug)]
+struct CompletionMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<CompletionToolCall<'a>>>,
+    // which call a "tool" message answers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct CompletionToolCall<'a> {
+    id: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    function: CompletionFunction<'a>,
+}
+
+#[derive(Serialize, Debug)]
+struct CompletionFunction<'a> {
+    name: &'a str,
+    // a JSON document as a string, where Ollama takes the object itself
+    arguments: String,
+}
+
+impl<'a> From<&'a ChatRequest> for CompletionRequest<'a> {
+    fn from(chat_request: &'a ChatRequest) -> Self {
+        let options = chat_request.options.as_ref();
+        // Ollama's tool calls have no ids, so each one gets its own here and the tool
+        // results that follow answer them in order
+        let mut unanswered: VecDeque<String> = VecDeque::new();
+        let messages = chat_request
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| {
+                let tool_calls = message.tool_calls.as_ref().map(|tool_calls| {
+                    tool_calls
+                        .iter()
+                        .enumerate()
+                        .map(|(call, tool_call)| {
+                            let id = format!("call_{}_{}", index, call);
+                            unanswered.push_back(id.clone());
+                            completion_tool_call(id, tool_call)
+                        })
+                        .collect()
+                });
+                let tool_call_id = (message.role == "tool")
+                    .then(|| unanswered.pop_front())
+                    .flatten();
+                CompletionMessage {
+                    role: &message.role,
+                    content: &message.content,
+                    tool_calls,
+                    tool_call_id,
+                }
+            })
+            .collect();
+        Self {
+            model: &chat_request.model,
+            messages,
+            stream: true,
+            temperature: options.and_then(|options| options.temperature),
+            top_p: options.and_then(|options| options.top_p),
+            seed: options.and_then(|options| options.seed),
+            tools: chat_request.tools.as_deref(),
+        }
+    }
+}
+
+fn completion_tool_call(id: String, tool_call: &ToolCall) -> CompletionToolCall<'_> {
+    CompletionToolCall {
+        id,
+        kind: "function",
+        function: CompletionFunction {
+            name: &tool_call.function.name,
+            arguments: tool_call.function.arguments.to_string(),
+        },
+    }
+}
+
+// GET /v1/models, which only knows ids and creation times
+#[derive(Deserialize, Debug)]
+pub struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ModelEntry {
+    id: String,
+    #[serde(default)]
+    created: i64,
+}
+
+impl ModelsResponse {
+    pub fn into_model_list(self) -> ModelList {
+        ModelList {
+            models: self
+                .data
+                .into_iter()
+                .map(|entry| Model {
+                    name: entry.id.clone(),
+                    model: entry.id,
+                    modified_at: chrono::DateTime::from_timestamp(entry.created, 0)
+                        .map(|created| created.to_rfc3339())
+                        .unwrap_or_default(),
+                    ..Model::default()
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+
+// An RFC3339 timestamp as "3 days ago"; None when it doesn't parse
+pub fn relative_time(timestamp: &str, now: DateTime<Utc>) -> Option<String> {
+    let then = DateTime::parse_from_rfc3339(timestamp).ok()?;
+    let seconds = now.signed_duration_since(then).num_seconds();
+    // clocks drift, a timestamp slightly in the future is still "just now"
+    if seconds < 60 {
+        return Some("just now".to_string());
+    }
+
+    let (count, unit) = match seconds {
+        ..3_600 => (seconds / 60, "minute"),
+        3_600..86_400 => (seconds / 3_600, "hour"),
+        86_400..2_592_000 => (seconds / 86_400, "day"),
+        2_592_000..31_536_000 => (seconds / 2_592_000, "month"),
+        _ => (seconds / 31_536_000, "year"),
+    };
+    let plural = if count == 1 { "" } else { "s" };
+    Some(format!("{} {}{} ago", count, unit, plural))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-06-15T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn recent_and_future_are_just_now() {
+        assert_eq!(
+            relative_time("2024-06-15T11:59:30Z", now()).as_deref(),
+            Some("just now")
+        );
+        assert_eq!(
+            relative_time("2024-06-15T12:05:00Z", now()).as_deref(),
+            Some("just now")
+        );
+    }
+
+    #[test]
+    fn picks_the_largest_whole_unit() {
+        let cases = [
+            ("2024-06-15T11:59:00Z", "1 minute ago"),
+            ("2024-06-15T11:15:00Z", "45 minutes ago"),
+            ("2024-06-15T09:00:00Z", "3 hours ago"),
+            ("2024-06-13T12:00:00Z", "2 days ago"),
+            ("2024-04-01T12:00:00Z", "2 months ago"),
+            ("2022-06-01T12:00:00Z", "2 years ago"),
+        ];
+        for (timestamp, expected) in cases {
+            assert_eq!(
+                relative_time(timestamp, now()).as_deref(),
+                Some(expected),
+                "{}",
+                timestamp
+            );
+        }
+    }
+
+    // the /api/tags format: nanoseconds and a local offset
+    #[test]
+    fn parses_ollama_timestamps() {
+        assert_eq!(
+            relative_time("2024-06-15T06:30:00.123456789-04:00", now()).as_deref(),
+            Some("1 hour ago")
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(relative_time("yesterday", now()), None);
+    }
+}
@@ -0,0 +1,111 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::Chat;
+
+pub struct SearchMatch {
+    pub chat_index: usize,
+    // byte range within the chat's content
+    pub range: Range<usize>,
+}
+
+// Search over the chat log; plain queries are matched literally, case-insensitive by default
+pub struct ChatSearch {
+    pub query: String,
+    pub case_sensitive: bool,
+    pub regex: bool,
+    // true while the query is being typed, false once confirmed with Enter
+    pub editing: bool,
+    pub matches: Vec<SearchMatch>,
+    pub current: usize,
+    // set when the query is not a valid regex
+    pub error: Option<String>,
+    // where the chat was before searching, restored on cancel
+    pub previous_scroll: usize,
+    pub previous_follow_tail: bool,
+}
+
+impl ChatSearch {
+    pub fn new(previous_scroll: usize, previous_follow_tail: bool) -> Self {
+        Self {
+            query: String::new(),
+            case_sensitive: false,
+            regex: false,
+            editing: true,
+            matches: vec![],
+            current: 0,
+            error: None,
+            previous_scroll,
+            previous_follow_tail,
+        }
+    }
+
+    fn pattern(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+    }
+
+    pub fn update(&mut self, history: &[Chat]) {
+        self.matches.clear();
+        self.current = 0;
+        self.error = None;
+        if self.query.is_empty() {
+            return;
+        }
+
+        let pattern = match self.pattern() {
+            Ok(pattern) => pattern,
+            Err(error) => {
+                // only the last line of the regex error is short enough for the prompt
+                let message = error.to_string();
+                self.error = message.lines().last().map(str::to_string);
+                return;
+            }
+        };
+
+        for (chat_index, chat) in history.iter().enumerate() {
+            self.matches.extend(
+                pattern
+                    .find_iter(&chat.content)
+                    .filter(|found| !found.is_empty())
+                    .map(|found| SearchMatch {
+                        chat_index,
+                        range: found.range(),
+                    }),
+            );
+        }
+    }
+
+    pub fn current_match(&self) -> Option<&SearchMatch> {
+        self.matches.get(self.current)
+    }
+
+    pub fn next(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + 1) % self.matches.len();
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.current = (self.current + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    // Matches inside one chat, in order, flagged when it is the current one
+    pub fn matches_in(&self, chat_index: usize) -> Vec<(Range<usize>, bool)> {
+        self.matches
+            .iter()
+            .enumerate()
+            .filter(|(_, found)| found.chat_index == chat_index)
+            .map(|(index, found)| (found.range.clone(), index == self.current))
+            .collect()
+    }
+}
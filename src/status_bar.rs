@@ -0,0 +1,56 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Stylize,
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+
+// One line of status pieces, drawn in order with a separator between them
+pub struct StatusBar<'a> {
+    sections: Vec<Line<'a>>,
+    separator: &'static str,
+}
+
+impl<'a> StatusBar<'a> {
+    // `plain` swaps the box-drawing separator for ASCII
+    pub fn new(plain: bool) -> Self {
+        Self {
+            sections: vec![],
+            separator: if plain { " | " } else { " │ " },
+        }
+    }
+
+    pub fn section(mut self, section: impl Into<Line<'a>>) -> Self {
+        self.sections.push(section.into());
+        self
+    }
+
+    pub fn section_if(self, condition: bool, section: impl FnOnce() -> Line<'a>) -> Self {
+        if condition {
+            self.section(section())
+        } else {
+            self
+        }
+    }
+}
+
+impl Widget for StatusBar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut spans: Vec<Span> = vec![];
+        for (index, section) in self.sections.into_iter().enumerate() {
+            if index > 0 {
+                spans.push(self.separator.dim());
+            }
+            // each section keeps its own style, the line style is folded into its spans
+            let style = section.style;
+            spans.extend(section.spans.into_iter().map(|mut span| {
+                span.style = style.patch(span.style);
+                span
+            }));
+        }
+        Paragraph::new(Line::from(spans))
+            .centered()
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+// A reusable prompt from the config; `{{name}}` marks a placeholder to fill in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub prompt: String,
+}
+
+impl Template {
+    // Placeholder names in order of first appearance, without duplicates
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut placeholders: Vec<String> = vec![];
+        let mut rest = self.prompt.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + 2 + end].trim().to_string();
+            if !name.is_empty() && !placeholders.contains(&name) {
+                placeholders.push(name);
+            }
+            rest = &rest[start + 2 + end + 2..];
+        }
+        placeholders
+    }
+
+    pub fn fill(&self, values: &[(String, String)]) -> String {
+        let mut prompt = self.prompt.clone();
+        for (name, value) in values {
+            prompt = prompt.replace(&format!("{{{{{}}}}}", name), value);
+            prompt = prompt.replace(&format!("{{{{ {} }}}}", name), value);
+        }
+        prompt
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+// Machine-readable session log: one JSON object per request or response chunk, appended as it happens
+pub struct Transcript {
+    file: File,
+}
+
+#[derive(Serialize)]
+struct Entry<'a, T: Serialize> {
+    // milliseconds since the Unix epoch
+    timestamp: u64,
+    direction: &'a str,
+    // the path a request went to, e.g. "/api/chat"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    endpoint: Option<&'a str>,
+    payload: &'a T,
+}
+
+impl Transcript {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    pub fn request(&mut self, endpoint: &str, payload: &impl Serialize) -> io::Result<()> {
+        self.append("request", Some(endpoint), payload)
+    }
+
+    pub fn response(&mut self, payload: &impl Serialize) -> io::Result<()> {
+        self.append("response", None, payload)
+    }
+
+    fn append(
+        &mut self,
+        direction: &str,
+        endpoint: Option<&str>,
+        payload: &impl Serialize,
+    ) -> io::Result<()> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        let mut line = serde_json::to_vec(&Entry {
+            timestamp,
+            direction,
+            endpoint,
+            payload,
+        })?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}